@@ -0,0 +1,354 @@
+use super::space::{self, Approximation, Space};
+use crate::errors::{self, SourceCodePosition};
+use crate::loader;
+use crate::parser;
+use crate::runtime::expression::Expression;
+use crate::runtime::literal::Literal;
+use crate::runtime::pattern::Pattern;
+use crate::runtime::pattern_list::{PatternList, PatternPosition};
+use std::collections::{HashMap, HashSet};
+
+fn pattern_identifiers(pattern: &Pattern, identifiers: &mut HashMap<String, usize>) {
+    match pattern {
+        Pattern::Identifier(name, _) => *identifiers.entry(name.clone()).or_default() += 1,
+        Pattern::Tuple(parts) | Pattern::And(parts) => {
+            for part in parts {
+                pattern_identifiers(part, identifiers);
+            }
+        }
+        Pattern::Or(parts, _) => {
+            // Every part of an OR binds the same variables, so count the part
+            // that uses them the most instead of adding them all up
+            let mut most = HashMap::new();
+            for part in parts {
+                let mut part_identifiers = HashMap::new();
+                pattern_identifiers(part, &mut part_identifiers);
+                for (name, count) in part_identifiers {
+                    let entry = most.entry(name).or_default();
+                    *entry = count.max(*entry);
+                }
+            }
+            for (name, count) in most {
+                *identifiers.entry(name).or_default() += count;
+            }
+        }
+        Pattern::Literal(_) | Pattern::Any | Pattern::Expression(_) | Pattern::Range(..) => (),
+    }
+}
+
+fn pattern_expressions(pattern: &Pattern) -> Vec<&Expression> {
+    match pattern {
+        Pattern::Expression(expression) => vec![expression],
+        Pattern::Range(start, end) => start.iter().chain(end).map(|i| i.as_ref()).collect(),
+        Pattern::Tuple(parts) | Pattern::And(parts) | Pattern::Or(parts, _) => {
+            parts.iter().flat_map(pattern_expressions).collect()
+        }
+        Pattern::Literal(_) | Pattern::Identifier(..) | Pattern::Any => vec![],
+    }
+}
+
+// Where the variable is first bound in the pattern
+fn identifier_position<'a>(pattern: &'a Pattern, name: &str) -> Option<&'a PatternPosition> {
+    match pattern {
+        Pattern::Identifier(identifier, position) if identifier == name => Some(position),
+        Pattern::Tuple(parts) | Pattern::And(parts) | Pattern::Or(parts, _) => parts
+            .iter()
+            .find_map(|part| identifier_position(part, name)),
+        _ => None,
+    }
+}
+
+fn expression_names(
+    expression: &Expression,
+    variables: &mut HashSet<String>,
+    calls: &mut HashSet<String>,
+) {
+    match expression {
+        Expression::Variable(name, _) => {
+            variables.insert(name.clone());
+        }
+        Expression::FunctionCall(name, argument, _) => {
+            calls.insert(name.clone());
+            expression_names(argument, variables, calls);
+        }
+        Expression::Tuple(parts) => {
+            for part in parts {
+                expression_names(part, variables, calls);
+            }
+        }
+        Expression::BinaryOperator(_, a, b) => {
+            expression_names(a, variables, calls);
+            expression_names(b, variables, calls);
+        }
+        Expression::UnaryOperator(_, a) => expression_names(a, variables, calls),
+        Expression::Literal(_) => (),
+    }
+}
+
+fn rule_names(rule: &(Pattern, Expression, PatternPosition)) -> (HashSet<String>, HashSet<String>) {
+    let mut variables = HashSet::new();
+    let mut calls = HashSet::new();
+    for expression in pattern_expressions(&rule.0).into_iter().chain([&rule.1]) {
+        expression_names(expression, &mut variables, &mut calls);
+    }
+    (variables, calls)
+}
+
+fn unused_variables(
+    rule: &(Pattern, Expression, PatternPosition),
+    warnings: &mut Vec<errors::CellTailError>,
+) {
+    let mut identifiers = HashMap::new();
+    pattern_identifiers(&rule.0, &mut identifiers);
+    let (used, _) = rule_names(rule);
+
+    let mut unused: Vec<_> = identifiers
+        .into_iter()
+        .filter(|(name, count)| *count == 1 && !name.starts_with('_') && !used.contains(name))
+        .map(|(name, _)| name)
+        .collect();
+    unused.sort();
+
+    for name in unused {
+        let mut position = identifier_position(&rule.0, &name)
+            .unwrap_or(&rule.2)
+            .clone();
+        if let Some(file) = rule.2.get_file() {
+            position.set_file(file);
+        }
+        warnings.push(
+            errors::CellTailError::warning(
+                &position,
+                errors::WarningCode::UnusedVariable,
+                format!("Variable {name:?} is never used"),
+            )
+            .with_note(
+                "Use _ to match anything without binding a variable, or start the name with _"
+                    .to_owned(),
+            ),
+        );
+    }
+}
+
+fn rule_spaces(
+    rule: &(Pattern, Expression, PatternPosition),
+    approximation: Approximation,
+) -> Vec<Space> {
+    let mut identifiers = HashMap::new();
+    pattern_identifiers(&rule.0, &mut identifiers);
+    space::pattern_space(&rule.0, &identifiers, approximation)
+}
+
+fn unreachable_rules(list: &PatternList, warnings: &mut Vec<errors::CellTailError>) {
+    let matched: Vec<_> = list
+        .0
+        .iter()
+        .map(|rule| rule_spaces(rule, Approximation::Under))
+        .collect();
+    let mut unreachable = vec![false; list.0.len()];
+
+    for (index, rule) in list.0.iter().enumerate() {
+        let matches = rule_spaces(rule, Approximation::Over);
+
+        // Rules generated from the same statement by mirror or for are
+        // allowed to overlap, and unreachable rules don't shadow anything the
+        // rules before them didn't already
+        let overlapping: Vec<_> = list.0[..index]
+            .iter()
+            .zip(&matched)
+            .zip(&unreachable)
+            .filter(|((earlier, earlier_matches), unreachable)| {
+                !**unreachable
+                    && earlier.2 != rule.2
+                    && !space::intersect_all(earlier_matches, &matches).is_empty()
+            })
+            .map(|(earlier, _)| earlier)
+            .collect();
+        let shadowed = overlapping
+            .iter()
+            .try_fold(matches.clone(), |rest, (_, earlier_matches)| {
+                space::subtract_all(rest, earlier_matches)
+            })
+            .is_some_and(|rest| rest.is_empty());
+        if !shadowed {
+            continue;
+        }
+        unreachable[index] = true;
+
+        let single = overlapping.iter().find(|(_, earlier_matches)| {
+            space::subtract_all(matches.clone(), earlier_matches).is_some_and(|i| i.is_empty())
+        });
+        let warning = match single {
+            Some((earlier, _)) => errors::CellTailError::warning(
+                &rule.2,
+                errors::WarningCode::UnreachableRule,
+                "This rule can never match, an earlier rule already matches everything it does"
+                    .to_owned(),
+            )
+            .with_related_note(&earlier.2, "It is shadowed by this rule".to_owned()),
+            None => overlapping.iter().fold(
+                errors::CellTailError::warning(
+                    &rule.2,
+                    errors::WarningCode::UnreachableRule,
+                    "This rule can never match, earlier rules together already match everything it does"
+                        .to_owned(),
+                ),
+                |warning, (earlier, _)| {
+                    warning.with_related_note(&earlier.2, "It is partly shadowed by this rule".to_owned())
+                },
+            ),
+        };
+        warnings.push(warning);
+    }
+}
+
+// A function whose patterns all take tuples of the same length is only checked
+// for arguments of that length, anything else is most likely a mistake at the
+// call site rather than a missing pattern
+fn function_domain(function: &PatternList) -> Vec<Space> {
+    let lengths: HashSet<_> = function
+        .0
+        .iter()
+        .flat_map(|rule| rule_spaces(rule, Approximation::Over))
+        .map(|space| match space {
+            Space::Tuple(parts) => Some(parts.len()),
+            _ => None,
+        })
+        .collect();
+
+    match lengths.into_iter().collect::<Vec<_>>().as_slice() {
+        [Some(length)] => vec![Space::Tuple(vec![Space::Any; *length])],
+        _ => vec![Space::Any],
+    }
+}
+
+fn non_exhaustive_functions(program: &parser::Program, warnings: &mut Vec<errors::CellTailError>) {
+    for (name, function) in &program.functions {
+        let definition = &function.0[0].2;
+        if definition
+            .get_file()
+            .is_some_and(|i| i != loader::MAIN_FILE)
+        {
+            continue;
+        }
+
+        let missing = function
+            .0
+            .iter()
+            .try_fold(function_domain(function), |rest, rule| {
+                space::subtract_all(rest, &rule_spaces(rule, Approximation::Under))
+            });
+        if let Some(missing) = missing.as_ref().and_then(|i| i.first()) {
+            let warning = errors::CellTailError::warning(
+                definition,
+                errors::WarningCode::NonExhaustive,
+                format!(
+                    "Function {name:?} doesn't match every argument, for example {} isn't matched by any pattern",
+                    missing.example()
+                ),
+            );
+            warnings.push(if program.attributes.strict {
+                warning
+            } else {
+                warning.with_note("Calling a function with an argument that isn't matched results in null, set S=true to make it an error instead".to_owned())
+            });
+        }
+    }
+}
+
+fn unused_functions(program: &parser::Program, warnings: &mut Vec<errors::CellTailError>) {
+    let mut calls = HashSet::new();
+    for rule in program
+        .rules
+        .0
+        .iter()
+        .chain(program.functions.values().flat_map(|i| &i.0))
+    {
+        calls.extend(rule_names(rule).1);
+    }
+
+    for (name, function) in &program.functions {
+        let definition = &function.0[0].2;
+        // Imported libraries are allowed to define functions the program doesn't need
+        if calls.contains(name)
+            || definition
+                .get_file()
+                .is_some_and(|i| i != loader::MAIN_FILE)
+        {
+            continue;
+        }
+        warnings.push(errors::CellTailError::warning(
+            definition,
+            errors::WarningCode::UnusedFunction,
+            format!("Function {name:?} is never called"),
+        ));
+    }
+}
+
+fn duplicate_attributes(program: &parser::Program, warnings: &mut Vec<errors::CellTailError>) {
+    let mut seen = HashSet::new();
+    for (name, position) in &program.attribute_definitions {
+        if !seen.insert(name) {
+            warnings.push(errors::CellTailError::warning(
+                position,
+                errors::WarningCode::DuplicateAttribute,
+                format!("Attribute {name:?} was already set, the earlier value is ignored"),
+            ));
+        }
+    }
+}
+
+fn rule_results(program: &parser::Program, warnings: &mut Vec<errors::CellTailError>) {
+    for (_, expression, position) in &program.rules.0 {
+        let length = match expression {
+            Expression::Tuple(parts) => parts.len(),
+            Expression::Literal(Literal::Tuple(parts)) => parts.len(),
+            _ => continue,
+        };
+        if length != 3 {
+            warnings.push(
+                errors::CellTailError::warning(
+                    position,
+                    errors::WarningCode::RuleResult,
+                    format!("Rule results in a tuple of {length} elements instead of 3"),
+                )
+                .with_note(
+                    "A rule should result in a single value or a (left, down, right) tuple"
+                        .to_owned(),
+                ),
+            );
+        }
+    }
+}
+
+pub(super) fn lint_program(
+    program: &parser::Program,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) {
+    let mut warnings = vec![];
+
+    for rule in program
+        .rules
+        .0
+        .iter()
+        .chain(program.functions.values().flat_map(|i| &i.0))
+    {
+        unused_variables(rule, &mut warnings);
+    }
+    unreachable_rules(&program.rules, &mut warnings);
+    for function in program.functions.values() {
+        unreachable_rules(function, &mut warnings);
+    }
+    unused_functions(program, &mut warnings);
+    non_exhaustive_functions(program, &mut warnings);
+    duplicate_attributes(program, &mut warnings);
+    rule_results(program, &mut warnings);
+
+    // Rules generated by mirror and for templates share a position and would
+    // otherwise report the same warning multiple times
+    for warning in warnings {
+        if !diagnostics.contains(&warning) {
+            diagnostics.push(warning);
+        }
+    }
+}
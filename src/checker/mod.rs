@@ -2,8 +2,13 @@ use crate::errors;
 use crate::parser;
 use crate::runtime::expression;
 use crate::runtime::pattern;
+use crate::runtime::pattern_list::PatternPosition;
 use std::collections::HashSet;
 
+mod lints;
+mod shapes;
+mod space;
+
 #[derive(Copy, Clone)]
 enum CheckerMode<'a> {
     Function,
@@ -17,7 +22,7 @@ fn check_pattern(
 ) -> errors::CellTailResult<()> {
     match pat {
         pattern::Pattern::Any => Ok(()),
-        pattern::Pattern::Identifier(val) => {
+        pattern::Pattern::Identifier(val, _) => {
             variables.insert(val.clone());
             Ok(())
         }
@@ -35,7 +40,7 @@ fn check_pattern(
             }
             Ok(())
         }
-        pattern::Pattern::Or(tup) => {
+        pattern::Pattern::Or(tup, position) => {
             let all_equal = tup
                 .iter()
                 .map(|b| {
@@ -52,15 +57,15 @@ fn check_pattern(
                             Ok(u)
                         } else {
                             Err(errors::CellTailError::new(
-                                &errors::UnkownLocationError,
-                                format!("Parts of OR expression define different variables"),
+                                position,
+                                "Parts of OR expression define different variables".to_string(),
                             ))
                         }
                     }
                 })
                 .unwrap_or(Err(errors::CellTailError::new(
-                    &errors::UnkownLocationError,
-                    format!("Empty OR statement"),
+                    position,
+                    "Empty OR statement".to_string(),
                 )))?;
 
             *variables = all_equal;
@@ -89,12 +94,11 @@ fn check_expression(
         }
         expression::Expression::Tuple(z) => z
             .iter()
-            .map(|i| check_expression(i, variables, mode))
-            .collect::<errors::CellTailResult<()>>(),
+            .try_for_each(|i| check_expression(i, variables, mode)),
         expression::Expression::UnaryOperator(_, z) => check_expression(z, variables, mode),
-        expression::Expression::FunctionCall(funk, arguments) => match mode {
+        expression::Expression::FunctionCall(funk, arguments, position) => match mode {
             CheckerMode::Function => Err(errors::CellTailError::new(
-                &errors::UnkownLocationError,
+                position,
                 format!("Can't call function {funk:?} inside of a function"),
             )),
             CheckerMode::Pattern { function_names } => {
@@ -102,79 +106,104 @@ fn check_expression(
                     Ok(())
                 } else {
                     Err(errors::CellTailError::new(
-                        &errors::UnkownLocationError,
+                        position,
                         format!("Call to undefined function {funk:?}"),
-                    ))
+                    )
+                    .with_closest(funk, function_names.iter().map(String::as_str)))
                 }
             }
         }
         .and_then(|_| check_expression(arguments, variables, mode)),
-        expression::Expression::Variable(var) => {
-            if var == "N" {
-                Ok(())
-            } else if variables.contains(var) {
+        expression::Expression::Variable(var, position) => {
+            if var == "N" || variables.contains(var) {
                 Ok(())
             } else {
-                Err(errors::CellTailError::new(
-                    &errors::UnkownLocationError,
-                    format!("Reference to unkown variable {var:?}\nNote: In patterns variables must be used outside complex expressions before they can be used inside complex expressions.\nNote: Existing vars: {variables:?}"),
-                ))
+                let mut names: Vec<_> = variables.iter().map(String::as_str).collect();
+                names.sort_unstable();
+
+                let error = errors::CellTailError::new(
+                    position,
+                    format!("Reference to unkown variable {var:?}"),
+                )
+                .with_note("In patterns variables must be used outside complex expressions before they can be used inside complex expressions.".to_owned());
+                let error = if names.is_empty() {
+                    error.with_note("No variables are defined here".to_owned())
+                } else {
+                    error.with_note(format!("Variables defined here: {}", names.join(", ")))
+                };
+                Err(error.with_closest(var, names))
             }
         }
     }
 }
 
-pub fn check_program(program: &parser::Program) -> errors::CellTailResult<()> {
-    let function_names: Vec<_> = program.functions.iter().map(|i| i.0.clone()).collect();
-
-    for rule in &program.rules.0 {
-        let mut vars = HashSet::new();
+fn check_rule(
+    rule: &(pattern::Pattern, expression::Expression, PatternPosition),
+    function_names: &Vec<String>,
+) -> errors::CellTailResult<()> {
+    let mut vars = HashSet::new();
 
-        if let pattern::Pattern::Tuple(a) = &rule.0 {
-            if a.len() != 3 {
-                Err(errors::CellTailError::new(
-                    &errors::UnkownLocationError,
-                    format!(
-                        "One rule is matches {} elements instead of the required 3 (left, center, right) elements, but matches {:?}",
-                        a.len(),
-                        rule.0
-                    ),
-                ))?
-            }
+    if let pattern::Pattern::Tuple(a) = &rule.0 {
+        if a.len() != 3 {
+            Err(errors::CellTailError::new(
+                &rule.2,
+                format!(
+                    "One rule is matches {} elements instead of the required 3 (left, center, right) elements, but matches {:?}",
+                    a.len(),
+                    rule.0
+                ),
+            ))?
         }
+    }
 
-        errors::fallback_position(
-            check_pattern(
-                &rule.0,
-                CheckerMode::Pattern {
-                    function_names: &function_names,
-                },
-                &mut vars,
-            ),
-            &rule.2,
-        )?;
-        errors::fallback_position(
-            check_expression(
-                &rule.1,
-                &vars,
-                CheckerMode::Pattern {
-                    function_names: &function_names,
-                },
-            ),
-            &rule.2,
-        )?
+    errors::fallback_position(
+        check_pattern(&rule.0, CheckerMode::Pattern { function_names }, &mut vars),
+        &rule.2,
+    )?;
+    errors::fallback_position(
+        check_expression(&rule.1, &vars, CheckerMode::Pattern { function_names }),
+        &rule.2,
+    )
+}
+
+fn check_function_rule(
+    rule: &(pattern::Pattern, expression::Expression, PatternPosition),
+) -> errors::CellTailResult<()> {
+    let mut vars = HashSet::new();
+    errors::fallback_position(
+        check_pattern(&rule.0, CheckerMode::Function, &mut vars),
+        &rule.2,
+    )?;
+    errors::fallback_position(
+        check_expression(&rule.1, &vars, CheckerMode::Function),
+        &rule.2,
+    )
+}
+
+pub fn check_program(
+    program: &parser::Program,
+    infer_shapes: bool,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) {
+    let mut function_names: Vec<_> = program.functions.keys().cloned().collect();
+    function_names.sort_unstable();
+
+    for rule in &program.rules.0 {
+        if let Err(error) = check_rule(rule, &function_names) {
+            diagnostics.push(error);
+        }
     }
 
-    for function in &program.functions {
-        for rule in &function.1 .0 {
-            let mut vars = HashSet::new();
-            check_pattern(&rule.0, CheckerMode::Function, &mut vars)?;
-            errors::fallback_position(
-                check_expression(&rule.1, &vars, CheckerMode::Function),
-                &rule.2,
-            )?
+    for function in program.functions.values() {
+        for rule in &function.0 {
+            if let Err(error) = check_function_rule(rule) {
+                diagnostics.push(error);
+            }
         }
     }
 
-    Ok(())
+    lints::lint_program(program, diagnostics);
+    if infer_shapes {
+        shapes::check_shapes(program, diagnostics);
+    }
 }
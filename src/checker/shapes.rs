@@ -0,0 +1,217 @@
+use super::space::{self, Approximation, Space};
+use crate::errors;
+use crate::parser;
+use crate::runtime::expression::Expression;
+use crate::runtime::pattern::Pattern;
+use crate::runtime::pattern_list::{PatternList, PatternPosition};
+use std::collections::HashMap;
+use std::fmt;
+
+type Bindings = HashMap<String, Vec<Space>>;
+
+impl fmt::Display for Space {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Space::Any => write!(f, "_"),
+            Space::Null => write!(f, "N"),
+            Space::Numbers(a, b) if a == b => write!(f, "{a}"),
+            Space::Numbers(..) => write!(f, "number"),
+            Space::Tuple(parts) => write!(
+                f,
+                "({})",
+                parts
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Space::Tuples(_) => write!(f, "tuple"),
+        }
+    }
+}
+
+fn no_identifiers() -> HashMap<String, usize> {
+    HashMap::new()
+}
+
+fn is_numeric(spaces: &[Space]) -> bool {
+    spaces
+        .iter()
+        .all(|i| matches!(i, Space::Null | Space::Numbers(..)))
+}
+
+fn numeric() -> Vec<Space> {
+    vec![Space::Null, Space::Numbers(isize::MIN, isize::MAX)]
+}
+
+// The spaces of the elements at index in tuples of the given length
+fn tuple_elements(spaces: &[Space], index: usize, length: usize) -> Vec<Space> {
+    spaces
+        .iter()
+        .filter_map(|space| match space {
+            Space::Tuple(parts) if parts.len() == length => Some(parts[index].clone()),
+            Space::Any => Some(Space::Any),
+            Space::Tuples(excluded) if !excluded.contains(&length) => Some(Space::Any),
+            _ => None,
+        })
+        .collect()
+}
+
+// Records the values each variable of a pattern can be bound to, knowing the
+// pattern is matched against one of the given spaces
+fn bind(pattern: &Pattern, known: Vec<Space>, bindings: &mut Bindings) {
+    let known = space::intersect_all(
+        &known,
+        &space::pattern_space(pattern, &no_identifiers(), Approximation::Over),
+    );
+
+    match pattern {
+        Pattern::Identifier(name, _) => bindings.entry(name.clone()).or_default().extend(known),
+        Pattern::Tuple(parts) => {
+            for (index, part) in parts.iter().enumerate() {
+                bind(part, tuple_elements(&known, index, parts.len()), bindings);
+            }
+        }
+        Pattern::And(parts) | Pattern::Or(parts, _) => {
+            for part in parts {
+                bind(part, known.clone(), bindings);
+            }
+        }
+        Pattern::Literal(_) | Pattern::Any | Pattern::Expression(_) | Pattern::Range(..) => (),
+    }
+}
+
+fn expression_shapes(
+    expression: &Expression,
+    bindings: &Bindings,
+    functions: &HashMap<String, PatternList>,
+) -> Vec<Space> {
+    match expression {
+        Expression::Literal(literal) => vec![Space::from_literal(literal)],
+        Expression::Variable(name, _) if name == "N" => vec![Space::Null],
+        Expression::Variable(name, _) => bindings
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| vec![Space::Any]),
+        Expression::Tuple(parts) => {
+            let mut result = vec![vec![]];
+            for part in parts {
+                let part_shapes = expression_shapes(part, bindings, functions);
+                if result.len() * part_shapes.len() > 256 {
+                    return vec![Space::Tuple(vec![Space::Any; parts.len()])];
+                }
+                result = result
+                    .iter()
+                    .flat_map(|prefix| {
+                        part_shapes.iter().map(|i| {
+                            let mut tuple: Vec<Space> = prefix.clone();
+                            tuple.push(i.clone());
+                            tuple
+                        })
+                    })
+                    .collect();
+            }
+            result.into_iter().map(Space::Tuple).collect()
+        }
+        Expression::BinaryOperator(_, a, b) => {
+            if is_numeric(&expression_shapes(a, bindings, functions))
+                && is_numeric(&expression_shapes(b, bindings, functions))
+            {
+                numeric()
+            } else {
+                vec![Space::Any]
+            }
+        }
+        Expression::UnaryOperator(_, a) => {
+            if is_numeric(&expression_shapes(a, bindings, functions)) {
+                numeric()
+            } else {
+                vec![Space::Any]
+            }
+        }
+        Expression::FunctionCall(name, _, _) => match functions.get(name) {
+            // Functions can't call functions, so this doesn't recurse forever
+            Some(function) => function
+                .0
+                .iter()
+                .flat_map(|rule| rule_shapes(rule, functions))
+                .chain([Space::Null])
+                .collect(),
+            None => vec![Space::Any],
+        },
+    }
+}
+
+fn rule_shapes(
+    rule: &(Pattern, Expression, PatternPosition),
+    functions: &HashMap<String, PatternList>,
+) -> Vec<Space> {
+    let mut bindings = HashMap::new();
+    bind(&rule.0, vec![Space::Any], &mut bindings);
+    expression_shapes(&rule.1, &bindings, functions)
+}
+
+// The values a rule sends to the left and to the right. None when the shape
+// of the result isn't known
+fn emitted(result: &Space) -> Option<[Space; 2]> {
+    match result {
+        Space::Tuple(parts) if parts.len() == 3 => Some([parts[0].clone(), parts[2].clone()]),
+        Space::Null | Space::Numbers(..) => Some([Space::Null, Space::Null]),
+        _ => None,
+    }
+}
+
+pub(super) fn check_shapes(
+    program: &parser::Program,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) {
+    // What the rules accept from the left, from above and from the right
+    let accepted: Vec<Vec<Space>> = (0..3)
+        .map(|index| {
+            program
+                .rules
+                .0
+                .iter()
+                .flat_map(|rule| {
+                    tuple_elements(
+                        &space::pattern_space(&rule.0, &no_identifiers(), Approximation::Over),
+                        index,
+                        3,
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    // A value sent to the left arrives at the right of the neighbouring cell.
+    // Values sent down that no rule matches are how a cell settles on its
+    // output, so they aren't checked
+    let directions = [("to the left", 2), ("to the right", 0)];
+
+    for rule in &program.rules.0 {
+        for result in rule_shapes(rule, &program.functions) {
+            let Some(values) = emitted(&result) else {
+                continue;
+            };
+
+            for (value, (direction, index)) in values.iter().zip(directions) {
+                // Null is the absence of a value, cells only receiving null
+                // aren't evaluated
+                if *value == Space::Null
+                    || accepted[index].iter().any(|i| value.intersect(i).is_some())
+                {
+                    continue;
+                }
+
+                let warning = errors::CellTailError::warning(
+                    &rule.2,
+                    errors::WarningCode::ShapeMismatch,
+                    format!("This rule sends {value} {direction}, but no rule matches a value like that there"),
+                );
+                if !diagnostics.contains(&warning) {
+                    diagnostics.push(warning);
+                }
+            }
+        }
+    }
+}
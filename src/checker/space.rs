@@ -0,0 +1,269 @@
+use crate::runtime::expression::Expression;
+use crate::runtime::literal::Literal;
+use crate::runtime::pattern::Pattern;
+use std::collections::HashMap;
+
+// A set of values described by their shape, used to reason about which values
+// a pattern matches without running the program
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Space {
+    Any,
+    Null,
+    // All numbers from the first to the second one, inclusive
+    Numbers(isize, isize),
+    Tuple(Vec<Space>),
+    // Tuples of every length except the listed ones
+    Tuples(Vec<usize>),
+}
+
+// Some patterns depend on values only known at runtime, like expressions using
+// variables. Depending on the question those are assumed to match everything
+// (Over) or nothing (Under)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(super) enum Approximation {
+    Under,
+    Over,
+}
+
+// Subtracting tuples splits them into many pieces, give up instead of
+// spending forever on large patterns
+const MAX_SPACES: usize = 4096;
+
+impl Space {
+    fn split_any() -> Vec<Space> {
+        vec![
+            Space::Null,
+            Space::Numbers(isize::MIN, isize::MAX),
+            Space::Tuples(vec![]),
+        ]
+    }
+
+    pub(super) fn from_literal(literal: &Literal) -> Space {
+        match literal {
+            Literal::Null => Space::Null,
+            Literal::Number(n) => Space::Numbers(*n, *n),
+            Literal::Tuple(parts) => Space::Tuple(parts.iter().map(Space::from_literal).collect()),
+        }
+    }
+
+    pub(super) fn intersect(&self, other: &Space) -> Option<Space> {
+        match (self, other) {
+            (Space::Any, b) => Some(b.clone()),
+            (a, Space::Any) => Some(a.clone()),
+            (Space::Null, Space::Null) => Some(Space::Null),
+            (Space::Numbers(a, b), Space::Numbers(c, d)) => {
+                (a.max(c) <= b.min(d)).then(|| Space::Numbers(*a.max(c), *b.min(d)))
+            }
+            (Space::Tuple(a), Space::Tuple(b)) if a.len() == b.len() => a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| a.intersect(b))
+                .collect::<Option<_>>()
+                .map(Space::Tuple),
+            (Space::Tuple(a), Space::Tuples(excluded))
+            | (Space::Tuples(excluded), Space::Tuple(a)) => {
+                (!excluded.contains(&a.len())).then(|| Space::Tuple(a.clone()))
+            }
+            (Space::Tuples(a), Space::Tuples(b)) => {
+                let mut excluded = a.clone();
+                excluded.extend(b.iter().filter(|i| !a.contains(i)));
+                Some(Space::Tuples(excluded))
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn subtract(&self, other: &Space) -> Vec<Space> {
+        if self.intersect(other).is_none() {
+            return vec![self.clone()];
+        }
+
+        match (self, other) {
+            (_, Space::Any) => vec![],
+            (Space::Any, b) => Space::split_any()
+                .iter()
+                .flat_map(|i| i.subtract(b))
+                .collect(),
+            (Space::Numbers(a, b), Space::Numbers(c, d)) => {
+                let mut result = vec![];
+                if a < c {
+                    result.push(Space::Numbers(*a, c - 1));
+                }
+                if d < b {
+                    result.push(Space::Numbers(d + 1, *b));
+                }
+                result
+            }
+            (Space::Tuple(a), Space::Tuple(b)) => {
+                // Everything in a that differs from b in the first element,
+                // then everything that matches the first element but differs
+                // in the second one, and so on
+                let mut result = vec![];
+                for index in 0..a.len() {
+                    let prefix: Vec<_> = a[..index]
+                        .iter()
+                        .zip(&b[..index])
+                        .filter_map(|(a, b)| a.intersect(b))
+                        .collect();
+                    for part in a[index].subtract(&b[index]) {
+                        result.push(Space::Tuple(
+                            prefix
+                                .iter()
+                                .cloned()
+                                .chain([part])
+                                .chain(a[index + 1..].iter().cloned())
+                                .collect(),
+                        ));
+                    }
+                }
+                result
+            }
+            (Space::Tuples(excluded), Space::Tuple(b)) => {
+                let mut rest = excluded.clone();
+                rest.push(b.len());
+                [Space::Tuples(rest)]
+                    .into_iter()
+                    .chain(Space::Tuple(vec![Space::Any; b.len()]).subtract(other))
+                    .collect()
+            }
+            (Space::Tuples(a), Space::Tuples(b)) => b
+                .iter()
+                .filter(|i| !a.contains(i))
+                .map(|i| Space::Tuple(vec![Space::Any; *i]))
+                .collect(),
+            // Null - Null and tuples minus tuples of every length are empty
+            _ => vec![],
+        }
+    }
+
+    pub(super) fn example(&self) -> Literal {
+        match self {
+            Space::Any | Space::Null => Literal::Null,
+            Space::Numbers(a, b) => Literal::Number(0.clamp(*a, *b)),
+            Space::Tuple(parts) => Literal::Tuple(parts.iter().map(Space::example).collect()),
+            Space::Tuples(excluded) => {
+                Literal::Tuple(vec![
+                    Literal::Null;
+                    (0..).find(|i| !excluded.contains(i)).unwrap_or(0)
+                ])
+            }
+        }
+    }
+}
+
+pub(super) fn intersect_all(a: &[Space], b: &[Space]) -> Vec<Space> {
+    a.iter()
+        .flat_map(|a| b.iter().filter_map(|b| a.intersect(b)))
+        .collect()
+}
+
+// None when the result gets too large to compute
+pub(super) fn subtract_all(a: Vec<Space>, b: &[Space]) -> Option<Vec<Space>> {
+    b.iter().try_fold(a, |rest, b| {
+        let rest: Vec<_> = rest.iter().flat_map(|i| i.subtract(b)).collect();
+        (rest.len() <= MAX_SPACES).then_some(rest)
+    })
+}
+
+fn range_bound(bound: Option<&Literal>, lower: bool, approximation: Approximation) -> Vec<Space> {
+    // Ranges compare values by the ordering of literals, which puts null
+    // before all numbers and numbers before all tuples
+    match (bound, lower) {
+        (None, _) => vec![Space::Any],
+        (Some(Literal::Null), true) => vec![
+            Space::Numbers(isize::MIN, isize::MAX),
+            Space::Tuples(vec![]),
+        ],
+        (Some(Literal::Null), false) => vec![],
+        (Some(Literal::Number(n)), true) => n
+            .checked_add(1)
+            .map(|n| Space::Numbers(n, isize::MAX))
+            .into_iter()
+            .chain([Space::Tuples(vec![])])
+            .collect(),
+        (Some(Literal::Number(n)), false) => [Space::Null]
+            .into_iter()
+            .chain(n.checked_sub(1).map(|n| Space::Numbers(isize::MIN, n)))
+            .collect(),
+        (Some(Literal::Tuple(_)), _) => unknown(approximation),
+    }
+}
+
+fn constant_value(expression: &Expression) -> Option<Literal> {
+    if expression.is_constant() {
+        expression
+            .evaluate(&HashMap::new(), &HashMap::new(), true, &mut vec![])
+            .ok()
+    } else {
+        None
+    }
+}
+
+fn unknown(approximation: Approximation) -> Vec<Space> {
+    match approximation {
+        Approximation::Under => vec![],
+        Approximation::Over => vec![Space::Any],
+    }
+}
+
+// The values a pattern matches. Identifiers listed more than once in
+// identifiers have to be equal to each other, so they can't be described by a
+// space and are approximated
+pub(super) fn pattern_space(
+    pattern: &Pattern,
+    identifiers: &HashMap<String, usize>,
+    approximation: Approximation,
+) -> Vec<Space> {
+    match pattern {
+        Pattern::Any => vec![Space::Any],
+        Pattern::Identifier(name, _) => {
+            if identifiers.get(name).is_some_and(|i| *i > 1) {
+                unknown(approximation)
+            } else {
+                vec![Space::Any]
+            }
+        }
+        Pattern::Literal(literal) => vec![Space::from_literal(literal)],
+        Pattern::Expression(expression) => match constant_value(expression) {
+            Some(value) => vec![Space::from_literal(&value)],
+            None => unknown(approximation),
+        },
+        Pattern::Tuple(parts) => {
+            let mut result = vec![vec![]];
+            for part in parts {
+                let part_spaces = pattern_space(part, identifiers, approximation);
+                if result.len() * part_spaces.len() > MAX_SPACES {
+                    return unknown(approximation);
+                }
+                result = result
+                    .iter()
+                    .flat_map(|prefix| {
+                        part_spaces.iter().map(|i| {
+                            let mut tuple = prefix.clone();
+                            tuple.push(i.clone());
+                            tuple
+                        })
+                    })
+                    .collect();
+            }
+            result.into_iter().map(Space::Tuple).collect()
+        }
+        Pattern::And(parts) => parts.iter().fold(vec![Space::Any], |result, part| {
+            intersect_all(&result, &pattern_space(part, identifiers, approximation))
+        }),
+        Pattern::Or(parts, _) => parts
+            .iter()
+            .flat_map(|i| pattern_space(i, identifiers, approximation))
+            .collect(),
+        Pattern::Range(start, end) => {
+            let bound = |expression: &Option<Box<Expression>>, lower| match expression {
+                None => range_bound(None, lower, approximation),
+                Some(expression) => match constant_value(expression) {
+                    Some(value) => range_bound(Some(&value), lower, approximation),
+                    None => unknown(approximation),
+                },
+            };
+            intersect_all(&bound(start, true), &bound(end, false))
+        }
+    }
+}
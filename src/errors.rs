@@ -1,8 +1,28 @@
 use serde::Serialize;
 
+pub type FileId = usize;
+
 pub trait SourceCodePosition {
     fn get_start(&self) -> Option<usize>;
     fn get_end(&self) -> Option<usize>;
+    fn get_file(&self) -> Option<FileId> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub contents: Vec<char>,
+}
+
+impl SourceFile {
+    pub fn new(name: String, contents: &str) -> SourceFile {
+        SourceFile {
+            name,
+            contents: contents.chars().collect(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -48,13 +68,126 @@ impl<T: SourceCodePosition> SourceCodePosition for Option<T> {
     fn get_end(&self) -> Option<usize> {
         self.as_ref().and_then(|i| i.get_end())
     }
+
+    fn get_file(&self) -> Option<FileId> {
+        self.as_ref().and_then(|i| i.get_file())
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCode {
+    UnusedVariable,
+    UnreachableRule,
+    UnusedFunction,
+    DuplicateAttribute,
+    RuleResult,
+    NonExhaustive,
+    ShapeMismatch,
+}
+
+impl WarningCode {
+    fn name(self) -> &'static str {
+        match self {
+            WarningCode::UnusedVariable => "unused-variable",
+            WarningCode::UnreachableRule => "unreachable-rule",
+            WarningCode::UnusedFunction => "unused-function",
+            WarningCode::DuplicateAttribute => "duplicate-attribute",
+            WarningCode::RuleResult => "rule-result",
+            WarningCode::NonExhaustive => "non-exhaustive",
+            WarningCode::ShapeMismatch => "shape-mismatch",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning(WarningCode),
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Help {
+    Note(String),
+    Suggestion(String),
+    Related {
+        note: String,
+        file: Option<FileId>,
+        start: Option<usize>,
+        end: Option<usize>,
+    },
+}
+
+// Where the interpreter was when a runtime error happened
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct RuntimeContext {
+    generation: Option<usize>,
+    cell: Option<usize>,
+    input: Option<String>,
+    rule: Option<(Option<FileId>, Option<usize>, Option<usize>)>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 pub struct CellTailError {
+    severity: Severity,
+    file: Option<FileId>,
     start: Option<usize>,
     end: Option<usize>,
     description: String,
+    help: Vec<Help>,
+    context: Option<Box<RuntimeContext>>,
+}
+
+impl SourceCodePosition for CellTailError {
+    fn get_start(&self) -> Option<usize> {
+        self.start
+    }
+    fn get_end(&self) -> Option<usize> {
+        self.end
+    }
+    fn get_file(&self) -> Option<FileId> {
+        self.file
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonPosition {
+    byte: usize,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonDiagnostic<'a> {
+    severity: &'static str,
+    code: Option<&'static str>,
+    message: &'a str,
+    file: Option<&'a str>,
+    start: Option<JsonPosition>,
+    end: Option<JsonPosition>,
+    notes: Vec<&'a str>,
+    suggestions: Vec<&'a str>,
+    related: Vec<JsonRelated<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<JsonContext<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonContext<'a> {
+    generation: Option<usize>,
+    cell: Option<usize>,
+    input: Option<&'a str>,
+    rule: Option<JsonRelated<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRelated<'a> {
+    message: &'a str,
+    file: Option<&'a str>,
+    start: Option<JsonPosition>,
+    end: Option<JsonPosition>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -70,34 +203,137 @@ impl CellTailError {
     where
         T: SourceCodePosition + std::fmt::Debug,
     {
-        if location.get_end().is_some() && location.get_start() > location.get_end() {
-            panic!(
-                "{:?} is invalid: start > end {:?} {:?}",
-                location,
-                location.get_start(),
-                location.get_end()
-            );
+        debug_assert!(
+            location.get_end().is_none() || location.get_start() <= location.get_end(),
+            "{location:?} is invalid: start > end"
+        );
+        let start = location.get_start();
+        CellTailError {
+            severity: Severity::Error,
+            file: location.get_file(),
+            start,
+            // Release builds clamp an invalid range instead of panicking
+            end: location.get_end().map(|end| end.max(start.unwrap_or(end))),
+            description: message,
+            help: vec![],
+            context: None,
         }
+    }
+
+    pub fn warning<T>(location: &T, code: WarningCode, message: String) -> CellTailError
+    where
+        T: SourceCodePosition + std::fmt::Debug,
+    {
         CellTailError {
+            severity: Severity::Warning(code),
+            ..CellTailError::new(location, message)
+        }
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.warning_code().is_some()
+    }
+
+    fn warning_code(&self) -> Option<&'static str> {
+        match self.severity {
+            Severity::Error => None,
+            Severity::Warning(code) => Some(code.name()),
+        }
+    }
+
+    pub fn with_note(mut self, note: String) -> CellTailError {
+        self.help.push(Help::Note(note));
+        self
+    }
+
+    pub fn with_related_note<T: SourceCodePosition>(
+        mut self,
+        location: &T,
+        note: String,
+    ) -> CellTailError {
+        self.help.push(Help::Related {
+            note,
+            file: location.get_file(),
             start: location.get_start(),
             end: location.get_end(),
-            description: message,
+        });
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: String) -> CellTailError {
+        self.help.push(Help::Suggestion(suggestion));
+        self
+    }
+
+    fn context(&mut self) -> &mut RuntimeContext {
+        self.context.get_or_insert_with(Default::default)
+    }
+
+    pub fn in_generation(mut self, generation: usize) -> CellTailError {
+        self.context().generation = Some(generation);
+        self
+    }
+
+    pub fn at_cell<T: std::fmt::Display>(mut self, cell: usize, input: &T) -> CellTailError {
+        let context = self.context();
+        context.cell = Some(cell);
+        context.input = Some(input.to_string());
+        self
+    }
+
+    // Rules are applied from the inside out, so the outermost rule, the one
+    // matching the cell, is the one that is kept
+    pub fn in_rule<T: SourceCodePosition>(mut self, rule: &T) -> CellTailError {
+        self.context().rule = Some((rule.get_file(), rule.get_start(), rule.get_end()));
+        self
+    }
+
+    // Suggests the candidate closest to a misspelled name, if any is close
+    // enough to be a likely typo
+    pub fn with_closest<'a, I: IntoIterator<Item = &'a str>>(
+        self,
+        name: &str,
+        candidates: I,
+    ) -> CellTailError {
+        match closest(name, candidates) {
+            Some(candidate) => self.with_suggestion(candidate.to_owned()),
+            None => self,
         }
     }
 
-    pub fn map_description<T: Fn(String)->String>(self, function: T) -> CellTailError {
+    pub fn map_description<T: Fn(String) -> String>(self, function: T) -> CellTailError {
         CellTailError {
             description: function(self.description),
-            start: self.start,
-            end: self.end
+            ..self
+        }
+    }
+
+    pub fn in_file(self, file: FileId) -> CellTailError {
+        CellTailError {
+            file: self.file.or(Some(file)),
+            ..self
         }
     }
 
+    fn notes(&self) -> impl Iterator<Item = &str> {
+        self.help.iter().filter_map(|i| match i {
+            Help::Note(note) => Some(note.as_str()),
+            Help::Suggestion(_) | Help::Related { .. } => None,
+        })
+    }
+
+    fn suggestions(&self) -> impl Iterator<Item = &str> {
+        self.help.iter().filter_map(|i| match i {
+            Help::Suggestion(suggestion) => Some(suggestion.as_str()),
+            Help::Note(_) | Help::Related { .. } => None,
+        })
+    }
+
     fn is_location_known(&self) -> bool {
         self.start.is_some() || self.end.is_some()
     }
 
-    fn get_line_number(source: &Vec<char>, position: usize) -> LinePosition {
+    fn get_line_number(source: &[char], position: usize) -> LinePosition {
         let lines: Vec<_> = source
             .iter()
             .enumerate()
@@ -109,13 +345,11 @@ impl CellTailError {
         let previous_line = lines
             .iter()
             .rev()
-            .filter(|(_, original_pos)| *original_pos < position)
-            .next();
+            .find(|(_, original_pos)| *original_pos < position);
 
         let next_line = lines
             .iter()
-            .filter(|(_, original_pos)| *original_pos >= position)
-            .next();
+            .find(|(_, original_pos)| *original_pos >= position);
 
         match (previous_line, next_line) {
             (None, None) => LinePosition {
@@ -148,7 +382,7 @@ impl CellTailError {
     }
 
     fn highlight_line<T: std::io::Write>(
-        source: &Vec<char>,
+        source: &[char],
         line_start: usize,
         error_start: usize,
         line_end: usize,
@@ -173,7 +407,7 @@ impl CellTailError {
     }
 
     fn highlight_error<T: std::io::Write>(
-        source: &Vec<char>,
+        source: &[char],
         start_info: LinePosition,
         end_info: LinePosition,
         output: &mut T,
@@ -207,14 +441,23 @@ impl CellTailError {
         }
     }
 
-    pub fn print<T: std::io::Write>(&self, source: Vec<char>, destination: &mut T) {
-        shell_tools::set_color(31, destination);
-        writeln!(destination, "There was a error running the code").unwrap();
-        shell_tools::set_color(0, destination);
+    fn print_location<T: std::io::Write>(
+        sources: &[SourceFile],
+        file: Option<FileId>,
+        start: Option<usize>,
+        end: Option<usize>,
+        show_file: bool,
+        destination: &mut T,
+    ) {
+        let source_file = &sources[file.unwrap_or(0)];
+        if show_file && (start.is_some() || end.is_some()) {
+            writeln!(destination, "In {}:", source_file.name).unwrap();
+        }
+        let source = &source_file.contents;
 
-        if let (Some(start_pos), Some(end_pos)) = (self.start, self.end) {
-            let line_info = Self::get_line_number(&source, start_pos);
-            let line_end_info = Self::get_line_number(&source, end_pos);
+        if let (Some(start_pos), Some(end_pos)) = (start, end) {
+            let line_info = Self::get_line_number(source, start_pos);
+            let line_end_info = Self::get_line_number(source, end_pos);
 
             writeln!(
                 destination,
@@ -226,9 +469,9 @@ impl CellTailError {
             )
             .unwrap();
 
-            Self::highlight_error(&source, line_info, line_end_info, destination);
-        } else if let Some(pos) = self.start.or(self.end) {
-            let line_info = Self::get_line_number(&source, pos);
+            Self::highlight_error(source, line_info, line_end_info, destination);
+        } else if let Some(pos) = start.or(end) {
+            let line_info = Self::get_line_number(source, pos);
 
             writeln!(
                 destination,
@@ -238,7 +481,7 @@ impl CellTailError {
             .unwrap();
 
             Self::highlight_error(
-                &source,
+                source,
                 line_info,
                 LinePosition {
                     column_number: line_info.column_number + 1,
@@ -249,23 +492,334 @@ impl CellTailError {
         } else {
             writeln!(destination, "At an unkown location: ").unwrap()
         }
-        shell_tools::set_color(31, destination);
+    }
+
+    fn print_context<T: std::io::Write>(
+        context: &RuntimeContext,
+        sources: &[SourceFile],
+        file: Option<FileId>,
+        destination: &mut T,
+    ) {
+        let parts: Vec<_> = [
+            context.generation.map(|i| format!("generation {i}")),
+            context.cell.map(|i| format!("cell {i}")),
+            context.input.as_ref().map(|i| format!("input {i}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !parts.is_empty() {
+            writeln!(destination, "Note: in {}", parts.join(", ")).unwrap();
+        }
+        if let Some((rule_file, start, end)) = context.rule {
+            writeln!(destination, "Note: while applying this rule").unwrap();
+            let show_file = rule_file.unwrap_or(0) != file.unwrap_or(0);
+            Self::print_location(sources, rule_file, start, end, show_file, destination);
+        }
+    }
+
+    pub fn print<T: std::io::Write>(&self, sources: &[SourceFile], destination: &mut T) {
+        let color = if self.is_warning() { 33 } else { 31 };
+        shell_tools::set_color(color, destination);
+        match self.warning_code() {
+            Some(code) => writeln!(destination, "Warning [{code}]").unwrap(),
+            None => writeln!(destination, "There was a error running the code").unwrap(),
+        }
+        shell_tools::set_color(0, destination);
+
+        Self::print_location(
+            sources,
+            self.file,
+            self.start,
+            self.end,
+            sources.len() > 1,
+            destination,
+        );
+        shell_tools::set_color(color, destination);
         writeln!(destination, "{}", self.description).unwrap();
         shell_tools::set_color(0, destination);
+        if let Some(context) = &self.context {
+            Self::print_context(context, sources, self.file, destination);
+        }
+        for note in self.notes() {
+            writeln!(destination, "Note: {note}").unwrap();
+        }
+        for suggestion in self.suggestions() {
+            writeln!(destination, "Help: did you mean {suggestion:?}?").unwrap();
+        }
+        for help in &self.help {
+            if let Help::Related {
+                note,
+                file,
+                start,
+                end,
+            } = help
+            {
+                writeln!(destination, "Note: {note}").unwrap();
+                Self::print_location(
+                    sources,
+                    *file,
+                    *start,
+                    *end,
+                    *file != self.file,
+                    destination,
+                );
+            }
+        }
+        if let Some(code) = self.warning_code() {
+            writeln!(
+                destination,
+                "Help: add \"# allow: {code}\" above this line to silence this warning"
+            )
+            .unwrap();
+        }
+    }
+
+    fn json_position(source: &[char], position: usize) -> JsonPosition {
+        let position = position.min(source.len());
+        let line_start = source[..position]
+            .iter()
+            .rposition(|i| *i == '\n')
+            .map_or(0, |i| i + 1);
+
+        JsonPosition {
+            byte: source[..position].iter().map(|i| i.len_utf8()).sum(),
+            line: source[..position].iter().filter(|i| **i == '\n').count() + 1,
+            column: position - line_start + 1,
+        }
+    }
+
+    fn json_span(
+        sources: &[SourceFile],
+        file: Option<FileId>,
+        start: Option<usize>,
+        end: Option<usize>,
+    ) -> (Option<&str>, Option<JsonPosition>, Option<JsonPosition>) {
+        let source_file = &sources[file.unwrap_or(0)];
+
+        (
+            (start.is_some() || end.is_some()).then_some(source_file.name.as_str()),
+            start
+                .or(end)
+                .map(|i| Self::json_position(&source_file.contents, i)),
+            end.or(start.map(|i| i + 1))
+                .map(|i| Self::json_position(&source_file.contents, i)),
+        )
+    }
+
+    fn to_json<'a>(&'a self, sources: &'a [SourceFile]) -> JsonDiagnostic<'a> {
+        let (file, start, end) = Self::json_span(sources, self.file, self.start, self.end);
+
+        JsonDiagnostic {
+            severity: if self.is_warning() {
+                "warning"
+            } else {
+                "error"
+            },
+            code: self.warning_code(),
+            message: &self.description,
+            file,
+            start,
+            end,
+            notes: self.notes().collect(),
+            suggestions: self.suggestions().collect(),
+            related: self
+                .help
+                .iter()
+                .filter_map(|i| match i {
+                    Help::Related {
+                        note,
+                        file,
+                        start,
+                        end,
+                    } => {
+                        let (file, start, end) = Self::json_span(sources, *file, *start, *end);
+                        Some(JsonRelated {
+                            message: note,
+                            file,
+                            start,
+                            end,
+                        })
+                    }
+                    Help::Note(_) | Help::Suggestion(_) => None,
+                })
+                .collect(),
+            context: self.context.as_ref().map(|context| JsonContext {
+                generation: context.generation,
+                cell: context.cell,
+                input: context.input.as_deref(),
+                rule: context.rule.map(|(file, start, end)| {
+                    let (file, start, end) = Self::json_span(sources, file, start, end);
+                    JsonRelated {
+                        message: "while applying this rule",
+                        file,
+                        start,
+                        end,
+                    }
+                }),
+            }),
+        }
     }
 }
 
 pub type CellTailResult<T> = Result<T, CellTailError>;
 
+// The optimal string alignment distance, the number of insertions, deletions,
+// substitutions and swaps of neighbouring characters from a to b
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+fn closest<'a, I: IntoIterator<Item = &'a str>>(name: &str, candidates: I) -> Option<&'a str> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    // Allow one mistake for every three characters, but never replacing the
+    // whole name
+    let max_distance = (name.len() / 3).max(1).min(name.len().saturating_sub(1));
+
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            let lowercase: Vec<char> = candidate.to_lowercase().chars().collect();
+            (edit_distance(&name, &lowercase), candidate)
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn sort_diagnostics(diagnostics: &mut [CellTailError]) {
+    diagnostics.sort_by_key(|i| (i.file.unwrap_or(0), !i.is_location_known(), i.start));
+}
+
+pub fn to_json(diagnostics: &mut [CellTailError], sources: &[SourceFile]) -> String {
+    sort_diagnostics(diagnostics);
+
+    serde_json::to_string(
+        &diagnostics
+            .iter()
+            .map(|i| i.to_json(sources))
+            .collect::<Vec<_>>(),
+    )
+    .expect("Failed to serialize diagnostics")
+}
+
+pub fn print_all<T: std::io::Write>(
+    diagnostics: &mut [CellTailError],
+    sources: &[SourceFile],
+    destination: &mut T,
+) {
+    sort_diagnostics(diagnostics);
+
+    for diagnostic in diagnostics.iter() {
+        diagnostic.print(sources, destination);
+    }
+
+    if diagnostics.len() > 1 {
+        let warnings = diagnostics.iter().filter(|i| i.is_warning()).count();
+        let counts = [
+            (diagnostics.len() - warnings, "errors"),
+            (warnings, "warnings"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, kind)| format!("{count} {kind}"))
+        .collect::<Vec<_>>()
+        .join(" and ");
+
+        shell_tools::set_color(31, destination);
+        writeln!(destination, "Found {counts}").unwrap();
+        shell_tools::set_color(0, destination);
+    }
+}
+
+pub fn has_errors(diagnostics: &[CellTailError]) -> bool {
+    diagnostics.iter().any(|i| !i.is_warning())
+}
+
+fn allowed_codes(line: &[char]) -> Vec<String> {
+    let line: String = line.iter().collect();
+    line.split_once('#')
+        .and_then(|(_, comment)| comment.trim().strip_prefix("allow:"))
+        .map(|codes| codes.split(',').map(|i| i.trim().to_owned()).collect())
+        .unwrap_or_default()
+}
+
+// A warning is silenced by a "# allow: code" comment at the end of the line
+// it points at or on its own line directly above it
+pub fn remove_suppressed(diagnostics: &mut Vec<CellTailError>, sources: &[SourceFile]) {
+    diagnostics.retain(|diagnostic| {
+        let (Some(code), Some(position)) = (diagnostic.warning_code(), diagnostic.start) else {
+            return true;
+        };
+        let source = &sources[diagnostic.file.unwrap_or(0)].contents;
+        let position = position.min(source.len());
+
+        let line_start = source[..position]
+            .iter()
+            .rposition(|i| *i == '\n')
+            .map_or(0, |i| i + 1);
+        let line_end = source[position..]
+            .iter()
+            .position(|i| *i == '\n')
+            .map_or(source.len(), |i| position + i);
+        let previous_line_start = source[..line_start.saturating_sub(1)]
+            .iter()
+            .rposition(|i| *i == '\n')
+            .map_or(0, |i| i + 1);
+
+        let allowed = allowed_codes(&source[line_start..line_end])
+            .into_iter()
+            .chain(
+                (line_start > 0)
+                    .then(|| &source[previous_line_start..line_start - 1])
+                    .filter(|line| line.iter().find(|i| !i.is_whitespace()) == Some(&'#'))
+                    .map(allowed_codes)
+                    .unwrap_or_default(),
+            )
+            .any(|i| i == code);
+
+        !allowed
+    });
+}
+
 pub fn fallback_position<T, E: SourceCodePosition + std::fmt::Debug>(
     res: CellTailResult<T>,
     fallback: &E,
 ) -> CellTailResult<T> {
     res.map_err(|u| {
         if u.is_location_known() {
-            u
+            CellTailError {
+                file: u.file.or(fallback.get_file()),
+                ..u
+            }
         } else {
-            CellTailError::new(fallback, u.description)
+            CellTailError {
+                severity: u.severity,
+                help: u.help,
+                context: u.context,
+                ..CellTailError::new(fallback, u.description)
+            }
         }
     })
 }
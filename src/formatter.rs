@@ -0,0 +1,401 @@
+use crate::errors;
+use crate::lexer::{self, LexerToken, TokenGroup};
+use crate::tokenizer::{self, Token, TokenKind};
+
+enum Item {
+    Blank,
+    Comment(String),
+    // Attributes and imports, printed without alignment
+    Plain(String, Option<String>),
+    // The function the rule belongs to, the parts of the pattern and of the
+    // result, and a comment at the end
+    Rule(Option<String>, Vec<String>, Vec<String>, Option<String>),
+}
+
+// The position of a token in the source, including the brackets of groups
+fn span(token: &LexerToken) -> (usize, usize) {
+    use errors::SourceCodePosition;
+
+    match token {
+        LexerToken::Group(TokenGroup {
+            brackets: Some(brackets),
+            ..
+        }) => *brackets,
+        _ => (
+            token.get_start().unwrap_or_default(),
+            token.get_end().unwrap_or_default(),
+        ),
+    }
+}
+
+fn text(source: &[char], start: usize, end: usize) -> String {
+    source[start..end].iter().collect()
+}
+
+// The number of tokens taken by modifiers like mirror, fn f and for x in [...]
+// at the start of a rule. Like in the parser, a modifier followed by an
+// operator is a variable instead
+fn modifiers_length(tokens: &[LexerToken]) -> usize {
+    use TokenKind::*;
+
+    let word = |index: usize, value: Option<&str>| {
+        matches!(tokens.get(index), Some(LexerToken::BasicToken(token))
+            if token.kind == Identifier && value.is_none_or(|value| token.value == value))
+    };
+    let operand = |index: usize| {
+        !matches!(
+            tokens.get(index).map(LexerToken::kind),
+            None | Some(Some(Comma | Operator(_) | Elipsis))
+        )
+    };
+
+    let mut length = 0;
+    loop {
+        if word(length, Some("mirror")) && operand(length + 1) {
+            length += 1;
+        } else if word(length, Some("fn")) && word(length + 1, None) {
+            length += 2;
+        } else if word(length, Some("for"))
+            && word(length + 1, None)
+            && word(length + 2, Some("in"))
+            && matches!(tokens.get(length + 3), Some(LexerToken::Group(_)))
+        {
+            length += 4;
+        } else {
+            return length;
+        }
+    }
+}
+
+// A - at the start of an expression or pattern negates instead
+fn is_binary_operator(tokens: &[LexerToken], index: usize) -> bool {
+    use TokenKind::*;
+
+    matches!(tokens[index].kind(), Some(Operator(_)))
+        && index > modifiers_length(tokens)
+        && !matches!(
+            tokens[index - 1].kind(),
+            Some(Operator(_) | Comma | Colon | Equals | Elipsis)
+        )
+}
+
+fn separator(tokens: &[LexerToken], index: usize, source: &[char]) -> &'static str {
+    use TokenKind::*;
+
+    let (previous, next) = (&tokens[index - 1], &tokens[index]);
+    if is_binary_operator(tokens, index) || is_binary_operator(tokens, index - 1) {
+        return " ";
+    }
+
+    match (previous.kind(), next.kind()) {
+        (_, Some(Comma | Colon | Semicolon)) => "",
+        (Some(Comma | Colon), _) => " ",
+        (Some(Equals), _) | (_, Some(Equals)) => "",
+        (Some(Operator('&' | '|')), _) | (_, Some(Operator('&' | '|'))) => " ",
+        // Two operators written together would become a single token
+        (Some(Operator(_)), Some(Operator(_))) => " ",
+        (Some(Operator(_)), _) => "",
+        (Some(Elipsis), _) | (_, Some(Elipsis)) => "",
+        (_, Some(Operator(_))) => " ",
+        (Some(Identifier | Number | String), Some(Identifier | Number | String)) => " ",
+        // Whether a space is needed depends on the meaning, like f(x) versus
+        // fn f (x), so keep the choice of the author
+        _ => {
+            let (_, previous_end) = span(previous);
+            let (next_start, _) = span(next);
+            if source[previous_end..next_start]
+                .iter()
+                .any(|i| i.is_whitespace())
+            {
+                " "
+            } else {
+                ""
+            }
+        }
+    }
+}
+
+fn render(tokens: &[LexerToken], source: &[char]) -> String {
+    let mut result = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 {
+            result.push_str(separator(tokens, index, source));
+        }
+        match token {
+            LexerToken::BasicToken(token) => result.push_str(&text(source, token.start, token.end)),
+            LexerToken::Group(group) => {
+                let (start, end) = span(token);
+                result.push(source[start]);
+                result.push_str(&render(&group.contents, source));
+                result.push(source[end - 1]);
+            }
+        }
+    }
+    result
+}
+
+// Removes the comments from a group, in the order they were written
+fn take_comments(group: &TokenGroup, comments: &mut Vec<Token>) -> TokenGroup {
+    let mut contents = vec![];
+    for token in &group.contents {
+        match token {
+            LexerToken::BasicToken(
+                token @ Token {
+                    kind: TokenKind::Comment,
+                    ..
+                },
+            ) => comments.push(token.clone()),
+            LexerToken::Group(inner) => {
+                contents.push(LexerToken::Group(take_comments(inner, comments)))
+            }
+            LexerToken::BasicToken(_) => contents.push(token.clone()),
+        }
+    }
+    TokenGroup {
+        contents,
+        ..group.clone()
+    }
+}
+
+fn split_parts(tokens: &[LexerToken], source: &[char]) -> Vec<String> {
+    tokens
+        .split(|i| i.kind() == Some(TokenKind::Comma))
+        .map(|part| render(part, source))
+        .collect()
+}
+
+fn statement_item(group: &TokenGroup, source: &[char]) -> Item {
+    let colon = group
+        .contents
+        .iter()
+        .position(|i| i.kind() == Some(TokenKind::Colon));
+
+    let function = match group.contents.as_slice() {
+        [LexerToken::BasicToken(keyword), LexerToken::BasicToken(name), ..]
+            if keyword.value == "fn" && name.kind == TokenKind::Identifier =>
+        {
+            Some(name.value.clone())
+        }
+        _ => None,
+    };
+
+    match colon {
+        Some(colon) if !group.contains(TokenKind::Equals) => Item::Rule(
+            function,
+            split_parts(&group.contents[..colon], source),
+            split_parts(&group.contents[colon + 1..], source),
+            None,
+        ),
+        _ => Item::Plain(render(&group.contents, source), None),
+    }
+}
+
+fn line_of(source: &[char], position: usize) -> usize {
+    source[..position].iter().filter(|i| **i == '\n').count()
+}
+
+fn has_blank_line(source: &[char], start: usize, end: usize) -> bool {
+    start < end && source[start..end].iter().filter(|i| **i == '\n').count() > 1
+}
+
+fn items(program: &TokenGroup, source: &[char]) -> Vec<Item> {
+    let mut items = vec![];
+    let mut previous_end: Option<usize> = None;
+
+    let push = |items: &mut Vec<Item>,
+                item: Item,
+                start: usize,
+                end: usize,
+                previous_end: &mut Option<usize>| {
+        if previous_end.is_some_and(|previous| has_blank_line(source, previous, start)) {
+            items.push(Item::Blank);
+        }
+        items.push(item);
+        *previous_end = Some(end);
+    };
+
+    for statement in &program.contents {
+        let LexerToken::Group(group) = statement else {
+            continue;
+        };
+        let mut comments = vec![];
+        let code = take_comments(group, &mut comments);
+        let code_start = code.contents.first().map_or(usize::MAX, |i| span(i).0);
+
+        for comment in comments {
+            let comment_text = text(source, comment.start, comment.end)
+                .trim_end()
+                .to_owned();
+            let trailing = previous_end.is_some_and(|previous| {
+                line_of(source, previous) == line_of(source, comment.start)
+            });
+
+            match items.last_mut() {
+                Some(Item::Rule(.., trailing_comment) | Item::Plain(_, trailing_comment))
+                    if trailing && trailing_comment.is_none() =>
+                {
+                    *trailing_comment = Some(comment_text);
+                    previous_end = Some(comment.end);
+                }
+                // Comments inside of a statement are moved in front of it
+                _ => push(
+                    &mut items,
+                    Item::Comment(comment_text),
+                    comment.start.min(code_start),
+                    comment.end,
+                    &mut previous_end,
+                ),
+            }
+        }
+
+        if let Some(last) = code.contents.last() {
+            push(
+                &mut items,
+                statement_item(&code, source),
+                code_start,
+                span(last).1,
+                &mut previous_end,
+            );
+        }
+    }
+
+    items
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+// The cells of a rule, with the separators attached
+fn cells(parts: &[String], last: char) -> Vec<String> {
+    parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let separator = if index + 1 == parts.len() { last } else { ',' };
+            format!("{part}{separator}")
+        })
+        .collect()
+}
+
+// The width of the widest cell in every column, ignoring the last cell of
+// each row as it is padded separately
+fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths = vec![];
+    for row in rows {
+        for (index, cell) in row[..row.len() - 1].iter().enumerate() {
+            if widths.len() <= index {
+                widths.push(0);
+            }
+            widths[index] = widths[index].max(width(cell));
+        }
+    }
+    widths
+}
+
+fn aligned(row: &[String], widths: &[usize]) -> String {
+    let mut line = String::new();
+    for (index, cell) in row[..row.len() - 1].iter().enumerate() {
+        line.push_str(&format!("{cell:<0$} ", widths[index]));
+    }
+    line.push_str(&row[row.len() - 1]);
+    line
+}
+
+// Rules of the same function written next to each other are aligned on the
+// commas between their parts and on the colon
+fn render_rules(rules: &[(&Vec<String>, &Vec<String>, &Option<String>)], output: &mut Vec<String>) {
+    let patterns: Vec<_> = rules.iter().map(|rule| cells(rule.0, ':')).collect();
+    let results: Vec<_> = rules.iter().map(|rule| cells(rule.1, ';')).collect();
+
+    let pattern_widths = column_widths(&patterns);
+    let patterns: Vec<_> = patterns
+        .iter()
+        .map(|i| aligned(i, &pattern_widths))
+        .collect();
+    let colon_column = patterns.iter().map(|i| width(i)).max().unwrap_or_default();
+    let result_widths = column_widths(&results);
+
+    for ((pattern, result), rule) in patterns.iter().zip(&results).zip(rules) {
+        let mut line = format!(
+            "{pattern:<0$} {1}",
+            colon_column,
+            aligned(result, &result_widths)
+        );
+        if let Some(comment) = rule.2 {
+            line.push_str(&format!(" {comment}"));
+        }
+        output.push(line);
+    }
+}
+
+// Comments inside of statements are moved in front of them, so they are
+// compared separately from the code
+fn significant_tokens(tokens: &[Token]) -> (Vec<(TokenKind, &str)>, Vec<&str>) {
+    let (comments, code): (Vec<_>, Vec<_>) =
+        tokens.iter().partition(|i| i.kind == TokenKind::Comment);
+    (
+        code.iter().map(|i| (i.kind, i.value.as_str())).collect(),
+        comments.iter().map(|i| i.value.trim_end()).collect(),
+    )
+}
+
+pub fn format(source: &[char], diagnostics: &mut Vec<errors::CellTailError>) -> Option<String> {
+    let tokens = tokenizer::tokenize(source, diagnostics);
+    let program = lexer::lex_with_comments(tokens.clone(), diagnostics);
+    if !diagnostics.is_empty() {
+        return None;
+    }
+
+    let items = items(&program, source);
+    let mut lines = vec![];
+    let mut index = 0;
+    while index < items.len() {
+        match &items[index] {
+            Item::Blank => lines.push(String::new()),
+            Item::Comment(comment) => lines.push(comment.clone()),
+            Item::Plain(statement, comment) => lines.push(match comment {
+                Some(comment) => format!("{statement}; {comment}"),
+                None => format!("{statement};"),
+            }),
+            Item::Rule(function, ..) => {
+                let rules: Vec<_> = items[index..]
+                    .iter()
+                    .map_while(|item| match item {
+                        Item::Rule(other, pattern, result, comment) if other == function => {
+                            Some((pattern, result, comment))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                render_rules(&rules, &mut lines);
+                index += rules.len();
+                continue;
+            }
+        }
+        index += 1;
+    }
+
+    let formatted: String = lines
+        .iter()
+        .map(|i| format!("{}\n", i.trim_end()))
+        .collect();
+
+    // The formatter only changes whitespace, make sure it didn't change the
+    // meaning of the program
+    let mut formatted_diagnostics = vec![];
+    let formatted_chars: Vec<char> = formatted.chars().collect();
+    let formatted_tokens = tokenizer::tokenize(&formatted_chars, &mut formatted_diagnostics);
+    if !formatted_diagnostics.is_empty()
+        || significant_tokens(&tokens) != significant_tokens(&formatted_tokens)
+    {
+        diagnostics.push(errors::CellTailError::new(
+            &errors::UnkownLocationError,
+            "Formatting changed the meaning of the program, this is a bug in the formatter"
+                .to_owned(),
+        ));
+        return None;
+    }
+
+    Some(formatted)
+}
@@ -0,0 +1,345 @@
+use crate::errors;
+use crate::lexer::{self, LexerToken, TokenGroup};
+use crate::parser;
+use crate::runtime::attributes::{constant_source, Attributes, IOFormat, InputSource};
+use crate::runtime::expression::Expression;
+use crate::runtime::literal::{string_literal, Literal};
+use crate::runtime::pattern::Pattern;
+use crate::runtime::pattern_list::PatternList;
+use crate::tokenizer::{self, Token, TokenKind};
+use std::collections::{HashMap, HashSet};
+
+fn format_letter(format: &IOFormat) -> char {
+    match format {
+        IOFormat::Characters => 'C',
+        IOFormat::Numbers => 'N',
+        IOFormat::Values => 'V',
+        IOFormat::Json => 'J',
+        IOFormat::Lines => 'L',
+        IOFormat::Bytes => 'B',
+    }
+}
+
+fn flag_letter(flag: bool) -> char {
+    if flag {
+        'T'
+    } else {
+        'F'
+    }
+}
+
+// The shortest way to write an attribute that parse_single_attribute accepts
+fn attribute(name: &str, attributes: &Attributes) -> String {
+    match name {
+        "Input" => match &attributes.input_mode {
+            InputSource::StdIn(format) => format!("I=I {}", format_letter(format)),
+            InputSource::Arg(format) => format!("I=C {}", format_letter(format)),
+            InputSource::Constant(values) => {
+                let numbers: Option<Vec<_>> = values
+                    .iter()
+                    .map(|i| match i {
+                        Literal::Number(n) => Some(*n),
+                        _ => None,
+                    })
+                    .collect();
+                let text = numbers.and_then(|i| string_literal(&i));
+                let value = [Some(constant_source(values, true)), text]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|i| i.len())
+                    .unwrap_or_default();
+                format!("I={value}")
+            }
+        },
+        "Output" => format!("O={}", format_letter(&attributes.output_mode)),
+        "Debug" => format!("D={}", flag_letter(attributes.debug)),
+        "Strict" => format!("S={}", flag_letter(attributes.strict)),
+        _ => format!("M={}", attributes.max_iterations.unwrap_or_default()),
+    }
+}
+
+// Attributes set to the value they have anyway can be left out
+fn is_default(name: &str, attributes: &Attributes) -> bool {
+    let default = Attributes::new();
+    match name {
+        "Input" => attributes.input_mode == default.input_mode,
+        "Output" => attributes.output_mode == default.output_mode,
+        "Debug" => attributes.debug == default.debug,
+        "Strict" => attributes.strict == default.strict,
+        _ => attributes.max_iterations == default.max_iterations,
+    }
+}
+
+fn number_text(token: &Token, source: &[char]) -> String {
+    let written: String = source[token.start..token.end].iter().collect();
+    // Character literals store their code point as the value
+    let value = if written.starts_with('\'') {
+        token.value.parse().ok()
+    } else {
+        let (radix, digits) = tokenizer::split_number_prefix(&token.value);
+        isize::from_str_radix(&digits.replace('_', ""), radix).ok()
+    };
+
+    match value {
+        Some(value) if value.to_string().len() < written.len() => value.to_string(),
+        _ => written,
+    }
+}
+
+fn token_text(token: &Token, source: &[char], renames: &HashMap<String, String>) -> String {
+    match token.kind {
+        TokenKind::Identifier => renames
+            .get(&token.value)
+            .cloned()
+            .unwrap_or_else(|| token.value.clone()),
+        TokenKind::Number => number_text(token, source),
+        _ => source[token.start..token.end].iter().collect(),
+    }
+}
+
+fn is_word_character(character: Option<char>) -> bool {
+    character.is_some_and(|i| i.is_alphanumeric() || i == '_')
+}
+
+fn render(
+    tokens: &[LexerToken],
+    source: &[char],
+    renames: &HashMap<String, String>,
+    output: &mut String,
+) {
+    let mut previous: Option<&LexerToken> = None;
+    for token in tokens {
+        let text = match token {
+            LexerToken::BasicToken(token) => token_text(token, source, renames),
+            LexerToken::Group(group) => {
+                let (Some(opening), Some(closing)) = (group.delimiter, group.brackets) else {
+                    continue;
+                };
+                let mut text = String::from(opening);
+                render(&group.contents, source, renames, &mut text);
+                text.push(source[closing.1 - 1]);
+                text
+            }
+        };
+
+        // Words would be read as one word, and operators like - - as --
+        let merges = is_word_character(output.chars().last())
+            && is_word_character(text.chars().next())
+            || matches!(
+                (previous.and_then(LexerToken::kind), token.kind()),
+                (Some(TokenKind::Operator(a)), Some(TokenKind::Operator(b))) if a == b
+            );
+        if merges {
+            output.push(' ');
+        }
+        output.push_str(&text);
+        previous = Some(token);
+    }
+}
+
+fn identifiers<'a>(tokens: &'a [LexerToken], result: &mut Vec<&'a str>) {
+    for token in tokens {
+        match token {
+            LexerToken::BasicToken(Token {
+                kind: TokenKind::Identifier,
+                value,
+                ..
+            }) => {
+                if !result.contains(&value.as_str()) {
+                    result.push(value)
+                }
+            }
+            LexerToken::BasicToken(_) => (),
+            LexerToken::Group(group) => identifiers(&group.contents, result),
+        }
+    }
+}
+
+// Gives every variable of a statement a single letter name. Function names and
+// keywords keep their name
+fn renames(group: &TokenGroup, reserved: &HashSet<&str>) -> HashMap<String, String> {
+    let mut names = vec![];
+    identifiers(&group.contents, &mut names);
+    let variables: Vec<_> = names.iter().filter(|i| !reserved.contains(*i)).collect();
+    let letters: Vec<_> = ('a'..='z')
+        .chain('A'..='Z')
+        .map(String::from)
+        .filter(|i| !reserved.contains(i.as_str()))
+        .collect();
+
+    if variables.len() > letters.len() {
+        return HashMap::new();
+    }
+    variables
+        .into_iter()
+        .zip(letters)
+        .map(|(variable, letter)| (variable.to_string(), letter))
+        .collect()
+}
+
+// The value of an expression without variables, constants can be written in
+// different ways like -1 or "a" and (97, N)
+fn constant(expression: &Expression) -> Option<Literal> {
+    if !expression.is_constant() {
+        return None;
+    }
+    expression
+        .evaluate(&HashMap::new(), &HashMap::new(), true, &mut vec![])
+        .ok()
+}
+
+fn constant_pattern(pattern: &Pattern) -> Option<Literal> {
+    match pattern {
+        Pattern::Literal(literal) => Some(literal.clone()),
+        Pattern::Tuple(parts) => parts
+            .iter()
+            .map(constant_pattern)
+            .collect::<Option<_>>()
+            .map(Literal::Tuple),
+        Pattern::Expression(expression) => constant(expression),
+        _ => None,
+    }
+}
+
+// Whether two patterns are the same, up to the names of their variables
+fn same_pattern(a: &Pattern, b: &Pattern, names: &mut Names) -> bool {
+    if let (Some(a), Some(b)) = (constant_pattern(a), constant_pattern(b)) {
+        return a == b;
+    }
+    match (a, b) {
+        (Pattern::Literal(a), Pattern::Literal(b)) => a == b,
+        (Pattern::Identifier(a, _), Pattern::Identifier(b, _)) => names.same(a, b),
+        (Pattern::Any, Pattern::Any) => true,
+        (Pattern::Tuple(a), Pattern::Tuple(b))
+        | (Pattern::And(a), Pattern::And(b))
+        | (Pattern::Or(a, _), Pattern::Or(b, _)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_pattern(a, b, names))
+        }
+        (Pattern::Expression(a), Pattern::Expression(b)) => same_expression(a, b, names),
+        (Pattern::Range(a_start, a_end), Pattern::Range(b_start, b_end)) => {
+            same_bound(a_start, b_start, names) && same_bound(a_end, b_end, names)
+        }
+        _ => false,
+    }
+}
+
+fn same_bound(a: &Option<Box<Expression>>, b: &Option<Box<Expression>>, names: &mut Names) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_expression(a, b, names),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn same_expression(a: &Expression, b: &Expression, names: &mut Names) -> bool {
+    if let (Some(a), Some(b)) = (constant(a), constant(b)) {
+        return a == b;
+    }
+    match (a, b) {
+        (Expression::Literal(a), Expression::Literal(b)) => a == b,
+        (Expression::Tuple(a), Expression::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_expression(a, b, names))
+        }
+        (Expression::BinaryOperator(a_op, a1, a2), Expression::BinaryOperator(b_op, b1, b2)) => {
+            a_op == b_op && same_expression(a1, b1, names) && same_expression(a2, b2, names)
+        }
+        (Expression::UnaryOperator(a_op, a), Expression::UnaryOperator(b_op, b)) => {
+            a_op == b_op && same_expression(a, b, names)
+        }
+        (Expression::FunctionCall(a_name, a, _), Expression::FunctionCall(b_name, b, _)) => {
+            a_name == b_name && same_expression(a, b, names)
+        }
+        (Expression::Variable(a, _), Expression::Variable(b, _)) => names.same(a, b),
+        _ => false,
+    }
+}
+
+// The renaming of variables seen so far, which has to be the same everywhere
+#[derive(Default)]
+struct Names(HashMap<String, String>, HashMap<String, String>);
+
+impl Names {
+    fn same(&mut self, a: &str, b: &str) -> bool {
+        let forward = self.0.entry(a.to_owned()).or_insert_with(|| b.to_owned());
+        let backward = self.1.entry(b.to_owned()).or_insert_with(|| a.to_owned());
+        forward == b && backward == a
+    }
+}
+
+fn same_rules(a: &PatternList, b: &PatternList) -> bool {
+    a.0.len() == b.0.len()
+        && a.0.iter().zip(&b.0).all(|(a, b)| {
+            let mut names = Names::default();
+            same_pattern(&a.0, &b.0, &mut names) && same_expression(&a.1, &b.1, &mut names)
+        })
+}
+
+pub fn same_program(a: &parser::Program, b: &parser::Program) -> bool {
+    a.attributes == b.attributes
+        && same_rules(&a.rules, &b.rules)
+        && a.functions.len() == b.functions.len()
+        && a.functions
+            .iter()
+            .all(|(name, a)| b.functions.get(name).is_some_and(|b| same_rules(a, b)))
+}
+
+// Prints the program in as few bytes as possible, checking that the result
+// still means the same
+pub fn golf(
+    sources: &mut Vec<errors::SourceFile>,
+    rename_variables: bool,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) -> Option<String> {
+    let program = crate::load_and_check(sources, false, diagnostics)?;
+    let source = &sources[crate::loader::MAIN_FILE].contents;
+    let statements = lexer::lex(tokenizer::tokenize(source, diagnostics), diagnostics);
+
+    let mut output = String::new();
+    let mut attribute_names = vec![];
+    for (name, _) in &program.attribute_definitions {
+        if !attribute_names.contains(name) && !is_default(name, &program.attributes) {
+            attribute_names.push(*name);
+            output.push_str(&attribute(name, &program.attributes));
+            output.push(';');
+        }
+    }
+
+    let reserved: HashSet<&str> = program
+        .functions
+        .keys()
+        .map(String::as_str)
+        .chain(["N", "_", "fn", "for", "in", "mirror", "import"])
+        .collect();
+    for statement in &statements.contents {
+        let LexerToken::Group(group) = statement else {
+            continue;
+        };
+        if group.contains(TokenKind::Equals) {
+            continue;
+        }
+        let renames = if rename_variables && group.contains(TokenKind::Colon) {
+            renames(group, &reserved)
+        } else {
+            HashMap::new()
+        };
+        render(&group.contents, source, &renames, &mut output);
+        output.push(';');
+    }
+
+    let mut golfed_sources = vec![errors::SourceFile::new(
+        sources[crate::loader::MAIN_FILE].name.clone(),
+        &output,
+    )];
+    let mut golfed_diagnostics = vec![];
+    let golfed = crate::load_and_check(&mut golfed_sources, false, &mut golfed_diagnostics);
+    if !golfed.is_some_and(|golfed| same_program(&program, &golfed)) {
+        diagnostics.push(errors::CellTailError::new(
+            &errors::UnkownLocationError,
+            "The golfed program doesn't mean the same as the original, this is a bug in the golfer"
+                .to_owned(),
+        ));
+        return None;
+    }
+
+    Some(output)
+}
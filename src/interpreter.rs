@@ -31,22 +31,33 @@ fn parse_literal(lit: Literal) -> (Literal, Literal, Literal) {
     }
 }
 
-fn interpret_iteration(cells: &Vec<Cell>, program: &parser::Program) -> Vec<Cell> {
-    let mut next_value = cells.clone();
+fn interpret_iteration(
+    cells: &[Cell],
+    program: &parser::Program,
+    warnings: &mut Vec<errors::CellTailError>,
+) -> errors::CellTailResult<Vec<Cell>> {
+    let mut next_value = cells.to_vec();
     let mut cell_offset = 0;
     for (index, cell) in cells.iter().enumerate() {
         if cell.value_from_left != Literal::Null
             || cell.value_from_top != Literal::Null
             || cell.value_from_right != Literal::Null
         {
-            if let Some(raw_result) = program.rules.apply_first_matching_pattern(
-                Literal::Tuple(vec![
-                    cell.value_from_left.clone(),
-                    cell.value_from_top.clone(),
-                    cell.value_from_right.clone(),
-                ]),
-                &program.functions,
-            ) {
+            let input = Literal::Tuple(vec![
+                cell.value_from_left.clone(),
+                cell.value_from_top.clone(),
+                cell.value_from_right.clone(),
+            ]);
+            if let Some(raw_result) = program
+                .rules
+                .apply_first_matching_pattern(
+                    input.clone(),
+                    &program.functions,
+                    program.attributes.strict,
+                    warnings,
+                )
+                .map_err(|error| error.at_cell(index, &input.display(is_text(program))))?
+            {
                 let result = parse_literal(raw_result);
 
                 if index == 0 && result.0 != Literal::Null {
@@ -69,23 +80,53 @@ fn interpret_iteration(cells: &Vec<Cell>, program: &parser::Program) -> Vec<Cell
         }
     }
 
-    return next_value;
+    Ok(next_value)
 }
 
-fn print_cells<T: std::io::Write>(cells: &Vec<Cell>, output_writer: &mut T) {
+// Values are shown as text when the program outputs characters or strings
+fn is_text(program: &parser::Program) -> bool {
+    matches!(
+        program.attributes.output_mode,
+        attributes::IOFormat::Characters
+            | attributes::IOFormat::Values
+            | attributes::IOFormat::Lines
+    )
+}
+
+fn print_cells<T: std::io::Write>(cells: &[Cell], text: bool, output_writer: &mut T) {
     for (index, cell) in cells.iter().enumerate() {
         if cell.value_from_top != Literal::Null || (index > 0 && index < cells.len() - 1) {
             crate::shell_tools::set_color(34, output_writer);
-            write!(output_writer, "\t({: >4} ", cell.value_from_left).unwrap();
+            write!(
+                output_writer,
+                "\t({: >4} ",
+                cell.value_from_left.display(text)
+            )
+            .unwrap();
             crate::shell_tools::set_color(33, output_writer);
-            write!(output_writer, ", {: >4}", cell.value_from_top).unwrap();
+            write!(output_writer, ", {: >4}", cell.value_from_top.display(text)).unwrap();
             crate::shell_tools::set_color(35, output_writer);
-            write!(output_writer, ", {: >4})", cell.value_from_right).unwrap();
+            write!(
+                output_writer,
+                ", {: >4})",
+                cell.value_from_right.display(text)
+            )
+            .unwrap();
             crate::shell_tools::set_color(0, output_writer);
         } else if index == 0 {
-            write!(output_writer, "{: >4}) ", cell.value_from_right).unwrap()
+            write!(
+                output_writer,
+                "{: >4}) ",
+                cell.value_from_right.display(text)
+            )
+            .unwrap()
         } else if index == cells.len() - 1 {
-            write!(output_writer, "({: >4},", cell.value_from_left).unwrap()
+            write!(
+                output_writer,
+                "({: >4},",
+                cell.value_from_left.display(text)
+            )
+            .unwrap()
         }
     }
     writeln!(output_writer).unwrap();
@@ -93,33 +134,34 @@ fn print_cells<T: std::io::Write>(cells: &Vec<Cell>, output_writer: &mut T) {
 
 fn interpret<T: std::io::Write>(
     program: &parser::Program,
-    input: Vec<isize>,
+    input: Vec<Literal>,
     output_writer: &mut T,
-) -> errors::CellTailResult<Vec<Option<isize>>> {
+    warnings: &mut Vec<errors::CellTailError>,
+) -> errors::CellTailResult<Vec<Literal>> {
     let mut cells: Vec<Cell> = input
         .iter()
         .map(|i| Cell {
-            value_from_left: Literal::Null,
-            value_from_top: Literal::Number(*i as isize),
-            value_from_right: Literal::Null,
+            value_from_top: i.clone(),
+            ..Cell::new()
         })
         .collect();
 
     if program.attributes.debug {
-        print_cells(&cells, output_writer);
+        print_cells(&cells, is_text(program), output_writer);
     }
 
-    let mut iteration_number = 0;
+    let mut iteration_number: usize = 0;
 
     let mut modified = true;
     while modified {
         iteration_number += 1;
-        let new_cells = interpret_iteration(&cells, program);
+        let new_cells = interpret_iteration(&cells, program, warnings)
+            .map_err(|error| error.in_generation(iteration_number))?;
         modified = cells != new_cells;
         cells = new_cells;
 
         if program.attributes.debug {
-            print_cells(&cells, output_writer);
+            print_cells(&cells, is_text(program), output_writer);
 
             if cfg!(not(target_arch = "wasm32")) {
                 std::thread::sleep(std::time::Duration::from_secs_f32(0.25))
@@ -127,49 +169,129 @@ fn interpret<T: std::io::Write>(
         }
 
         if let Some(max_iteration_number) = program.attributes.max_iterations {
-            if iteration_number > max_iteration_number {
+            if iteration_number as isize > max_iteration_number {
+                let attribute = program
+                    .attribute_definitions
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| *name == "Max")
+                    .map(|(_, position)| position.clone());
                 return Err(errors::CellTailError::new(
-                    &errors::UnkownLocationError,
+                    &attribute,
                     format!("Exceeded maximum iteration number {max_iteration_number}"),
-                ));
+                )
+                .in_generation(iteration_number));
             }
         }
     }
 
     Ok(cells
-        .iter()
-        .filter(|i| {
-            if let Literal::Null = i.value_from_top {
-                false
-            } else {
-                true
-            }
-        })
-        .map(|i| format_character_in_output(&i.value_from_top))
-        .collect::<Vec<_>>())
+        .into_iter()
+        .map(|i| i.value_from_top)
+        .filter(|i| *i != Literal::Null)
+        .collect())
 }
 
 fn format_character_in_output(value: &Literal) -> Option<isize> {
     match value {
         Literal::Number(k) => Some(*k),
-        Literal::Tuple(t) if t.len() >= 1 => format_character_in_output(&t[0]),
+        Literal::Tuple(t) if !t.is_empty() => format_character_in_output(&t[0]),
         _ => None,
     }
 }
 
-fn get_contents(data: &str, format: &attributes::IOFormat) -> errors::CellTailResult<Vec<isize>> {
+fn from_json(value: &serde_json::Value) -> errors::CellTailResult<Literal> {
+    Ok(match value {
+        serde_json::Value::Null => Literal::Null,
+        serde_json::Value::Number(number) => Literal::Number(
+            number
+                .as_i64()
+                .and_then(|i| i.try_into().ok())
+                .ok_or_else(|| {
+                    errors::CellTailError::new(
+                        &errors::UnkownLocationError,
+                        format!("Expected a whole number in the JSON input, found {number}"),
+                    )
+                })?,
+        ),
+        serde_json::Value::String(string) => Literal::new_string_literal(string),
+        serde_json::Value::Array(values) => Literal::Tuple(
+            values
+                .iter()
+                .map(from_json)
+                .collect::<errors::CellTailResult<_>>()?,
+        ),
+        other => Err(errors::CellTailError::new(
+            &errors::UnkownLocationError,
+            format!(
+                "Expected null, a number, a string or an array in the JSON input, found {other}"
+            ),
+        ))?,
+    })
+}
+
+fn output_character(value: Option<isize>) -> char {
+    match value {
+        Some(i) => char::from_u32(i.try_into().unwrap_or(0xFFFD)).unwrap_or('\u{FFFD}'),
+        _ => '?',
+    }
+}
+
+fn get_contents(
+    data: &[u8],
+    format: &attributes::IOFormat,
+) -> errors::CellTailResult<Vec<Literal>> {
+    if *format == attributes::IOFormat::Bytes {
+        return Ok(data.iter().map(|i| Literal::Number(*i as isize)).collect());
+    }
+    let data = std::str::from_utf8(data).map_err(|e| {
+        errors::CellTailError::new(
+            &errors::UnkownLocationError,
+            format!(
+                "The input is not valid UTF-8, use the BYTES input format for binary data: {e}"
+            ),
+        )
+    })?;
+
     match format {
-        attributes::IOFormat::Characters => Ok(data.chars().map(|i| i as u32 as isize).collect()),
+        attributes::IOFormat::Characters => Ok(data
+            .chars()
+            .map(|i| Literal::Number(i as u32 as isize))
+            .collect()),
         attributes::IOFormat::Numbers => data
-            .split(',')
-            .map(str::parse)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| {
+            .split(|i: char| i == ',' || i.is_whitespace())
+            .filter(|i| !i.is_empty())
+            .map(|i| {
+                i.parse().map(Literal::Number).map_err(|e| {
+                    errors::CellTailError::new(
+                        &errors::UnkownLocationError,
+                        format!("Failed to parse {i:?} in the input as a number: {e}"),
+                    )
+                })
+            })
+            .collect(),
+        // An empty line is N, so blank lines are dropped from the output
+        attributes::IOFormat::Lines => Ok(data.lines().map(Literal::new_string_literal).collect()),
+        // Every element of the array is the value of one cell
+        attributes::IOFormat::Json => {
+            let value: serde_json::Value = serde_json::from_str(data).map_err(|e| {
                 errors::CellTailError::new(
                     &errors::UnkownLocationError,
-                    format!("Failed to parse command line arguments: {:?}", e),
+                    format!("Failed to parse the input as JSON: {e}"),
                 )
-            }),
+            })?;
+            match value {
+                serde_json::Value::Array(values) => values.iter().map(from_json).collect(),
+                _ => Err(errors::CellTailError::new(
+                    &errors::UnkownLocationError,
+                    "Expected the JSON input to be an array with a value for every cell".to_owned(),
+                )),
+            }
+        }
+        format => Err(errors::CellTailError::new(
+            &errors::UnkownLocationError,
+            format!("{format} can only be used as an output mode"),
+        )),
     }
 }
 
@@ -177,16 +299,17 @@ pub fn run_program<T: std::io::Write>(
     program: parser::Program,
     command_line_arguments: Vec<String>,
     output: &mut T,
+    warnings: &mut Vec<errors::CellTailError>,
 ) -> errors::CellTailResult<()> {
     let input = match &program.attributes.input_mode {
         attributes::InputSource::Arg(m) => {
             if command_line_arguments.len() != 1 {
                 Err(errors::CellTailError::new(
                     &errors::UnkownLocationError,
-                    format!("Expected a command line argument"),
+                    "Expected a command line argument".to_string(),
                 ))?
             }
-            get_contents(&command_line_arguments[0], m)?
+            get_contents(command_line_arguments[0].as_bytes(), m)?
         }
         attributes::InputSource::StdIn(m) => {
             let mut file_contents: Vec<u8> = vec![];
@@ -194,40 +317,71 @@ pub fn run_program<T: std::io::Write>(
                 .read_to_end(&mut file_contents)
                 .expect("Failed to read contents of STDIN");
 
-            get_contents(std::str::from_utf8(&file_contents).unwrap(), m)?
+            get_contents(&file_contents, m)?
         }
-        attributes::InputSource::Constant(constant) => constant.to_vec(),
+        attributes::InputSource::Constant(constant) => constant.clone(),
     };
 
-    let result = interpret(&program, input, output)?;
+    let result = interpret(&program, input, output, warnings)?;
 
-    match &program.attributes.output_mode {
-        attributes::IOFormat::Characters => writeln!(
-            output,
-            "{}",
-            result
-                .iter()
-                .map(|i| match i {
-                    Some(i) =>
-                        char::from_u32((*i).try_into().unwrap_or(0xFFFD)).unwrap_or('\u{FFFD}'),
-                    _ => '?',
-                })
-                .collect::<String>()
-        )
-        .expect("Failed to print output"),
-        attributes::IOFormat::Numbers => writeln!(
-            output,
-            "{}",
-            result
+    let numbers = || result.iter().map(format_character_in_output);
+    let text = match &program.attributes.output_mode {
+        attributes::IOFormat::Characters => numbers().map(output_character).collect::<String>(),
+        attributes::IOFormat::Numbers => numbers()
+            .map(|i| match i {
+                Some(i) => format!("{}, ", i),
+                _ => "???, ".to_owned(),
+            })
+            .collect::<String>(),
+        attributes::IOFormat::Values => result
+            .iter()
+            .map(|value| {
+                if value.contains_single_tuple() {
+                    Err(errors::CellTailError::new(
+                        &errors::UnkownLocationError,
+                        format!("Can't output {value} as a value, tuples with one value can't be written in CellTail, use the JSON output mode instead"),
+                    ))
+                } else {
+                    Ok(value.source().to_string())
+                }
+            })
+            .collect::<errors::CellTailResult<Vec<_>>>()?
+            .join(", "),
+        attributes::IOFormat::Json => {
+            serde_json::Value::Array(result.iter().map(Literal::to_json).collect()).to_string()
+        }
+        // Every cell holds the characters of one line
+        attributes::IOFormat::Lines => result
+            .iter()
+            .map(|i| match i.as_list() {
+                Some(list) => list
+                    .into_iter()
+                    .map(|i| output_character(format_character_in_output(i)))
+                    .collect(),
+                None => output_character(format_character_in_output(i)).to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        // Raw bytes, without a newline at the end
+        attributes::IOFormat::Bytes => {
+            let bytes = result
                 .iter()
-                .map(|i| match i {
-                    Some(i) => format!("{}, ", i),
-                    _ => "???, ".to_owned(),
+                .map(|value| {
+                    format_character_in_output(value)
+                        .and_then(|i| u8::try_from(i).ok())
+                        .ok_or_else(|| {
+                            errors::CellTailError::new(
+                                &errors::UnkownLocationError,
+                                format!("Can't output {value} as a byte, bytes are numbers from 0 to 255"),
+                            )
+                        })
                 })
-                .collect::<String>()
-        )
-        .expect("Failed to print output"),
-    }
+                .collect::<errors::CellTailResult<Vec<u8>>>()?;
+            output.write_all(&bytes).expect("Failed to print output");
+            return Ok(());
+        }
+    };
+    writeln!(output, "{text}").expect("Failed to print output");
 
     Ok(())
 }
@@ -4,6 +4,9 @@ use crate::tokenizer::{Token, TokenKind};
 #[derive(Debug, Clone)]
 pub struct TokenGroup {
     pub delimiter: Option<char>,
+    // Where the opening and closing brackets are, for groups written in
+    // brackets. Used as the position of empty groups
+    pub brackets: Option<(usize, usize)>,
     pub contents: Vec<LexerToken>,
 }
 
@@ -27,11 +30,13 @@ impl TokenGroup {
         Some((
             TokenGroup {
                 delimiter: self.delimiter,
+                brackets: None,
                 contents: self.contents[..index].to_vec(),
             },
             middle_element.clone(),
             TokenGroup {
                 delimiter: self.delimiter,
+                brackets: None,
                 contents: self.contents[index + 1..].to_vec(),
             },
         ))
@@ -42,33 +47,40 @@ impl TokenGroup {
             return vec![];
         }
 
-        return self
-            .contents
+        self.contents
             .split(|b| match b {
                 LexerToken::BasicToken(b) => b.kind == kind,
                 _ => false,
             })
             .map(|b| TokenGroup {
                 delimiter: None,
+                brackets: None,
                 contents: b.to_vec(),
             })
-            .collect();
+            .collect()
     }
 
     pub fn contains(&self, kind: TokenKind) -> bool {
-        return self.contents.iter().any(|b| match b {
+        self.contents.iter().any(|b| match b {
             LexerToken::BasicToken(b) => b.kind == kind,
             _ => false,
-        });
+        })
     }
 }
 
 impl errors::SourceCodePosition for TokenGroup {
     fn get_start(&self) -> Option<usize> {
-        self.contents.first().and_then(|i| i.get_start())
+        self.contents
+            .iter()
+            .find_map(|i| i.get_start())
+            .or(self.brackets.map(|i| i.0))
     }
     fn get_end(&self) -> Option<usize> {
-        self.contents.last().and_then(|i| i.get_end())
+        self.contents
+            .iter()
+            .rev()
+            .find_map(|i| i.get_end())
+            .or(self.brackets.map(|i| i.1))
     }
 }
 
@@ -78,6 +90,16 @@ pub enum LexerToken {
     BasicToken(Token),
 }
 
+impl LexerToken {
+    // The kind of basic tokens, None for groups
+    pub fn kind(&self) -> Option<TokenKind> {
+        match self {
+            LexerToken::BasicToken(token) => Some(token.kind),
+            LexerToken::Group(_) => None,
+        }
+    }
+}
+
 impl errors::SourceCodePosition for LexerToken {
     fn get_start(&self) -> Option<usize> {
         match self {
@@ -93,92 +115,147 @@ impl errors::SourceCodePosition for LexerToken {
     }
 }
 
-pub fn lex(input: Vec<Token>) -> errors::CellTailResult<TokenGroup> {
-    let mut stack: Vec<TokenGroup> = vec![
-        TokenGroup {
-            delimiter: None,
-            contents: vec![],
-        },
-        TokenGroup {
-            delimiter: Some(';'),
-            contents: vec![],
-        },
-    ];
+fn closing_bracket(opening: Option<char>) -> Option<char> {
+    match opening {
+        Some('(') => Some(')'),
+        Some('[') => Some(']'),
+        Some('{') => Some('}'),
+        _ => None,
+    }
+}
+
+fn statement() -> TokenGroup {
+    TokenGroup {
+        delimiter: Some(';'),
+        brackets: None,
+        contents: vec![],
+    }
+}
+
+// Adds a token to the innermost open group, or to the program itself when no
+// group is open
+fn push_token(program: &mut Vec<LexerToken>, open: &mut [TokenGroup], token: LexerToken) {
+    match open.last_mut() {
+        Some(group) => group.contents.push(token),
+        None => program.push(token),
+    }
+}
+
+pub fn lex(input: Vec<Token>, diagnostics: &mut Vec<errors::CellTailError>) -> TokenGroup {
+    lex_tokens(input, false, diagnostics)
+}
+
+// Keeps comments in the groups they were written in, for tools that print the
+// source again. Comments after the last statement end up in a group of their
+// own without a semicolon
+pub fn lex_with_comments(
+    input: Vec<Token>,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) -> TokenGroup {
+    lex_tokens(input, true, diagnostics)
+}
+
+fn lex_tokens(
+    input: Vec<Token>,
+    keep_comments: bool,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) -> TokenGroup {
+    let mut program = vec![];
+    // The groups that are still open, starting with the current statement
+    let mut open: Vec<TokenGroup> = vec![statement()];
+    // After an error the rest of the statement is skipped
+    let mut skipping_statement = false;
 
     for token in input {
+        if skipping_statement {
+            skipping_statement = token.kind != TokenKind::Semicolon;
+            continue;
+        }
+
         match token {
             Token {
                 kind: TokenKind::OpeningBracket(character),
                 ..
-            } => stack.push(TokenGroup {
+            } => open.push(TokenGroup {
                 delimiter: Some(character),
+                brackets: Some((token.start, token.end)),
                 contents: vec![],
             }),
             Token {
                 kind: TokenKind::Semicolon,
                 ..
             } => {
-                let last_stack_value = stack.pop().expect("Unmatched closing bracket (type 6)");
+                let last_stack_value = open.pop().unwrap_or_else(statement);
                 if last_stack_value.delimiter != Some(';') {
-                    return Err(errors::CellTailError::new(
+                    diagnostics.push(errors::CellTailError::new(
                         &last_stack_value,
-                         format!("Unexpected ;, expected a {:?}, you may be missing a closing bracket (type 7)", last_stack_value.delimiter)));
+                        format!("Unexpected ;, expected a {:?}, you may be missing a closing bracket (type 7)", last_stack_value.delimiter)));
+                } else {
+                    program.push(LexerToken::Group(last_stack_value));
                 }
-                stack
-                    .last_mut()
-                    .expect("Unmatched closing bracket (type 8)")
-                    .contents
-                    .push(LexerToken::Group(last_stack_value));
-                stack.push(TokenGroup {
-                    delimiter: Some(';'),
-                    contents: vec![],
-                });
+                open = vec![statement()];
             }
             Token {
                 kind: TokenKind::Comment,
                 ..
-            } => (),
+            } => {
+                if keep_comments {
+                    push_token(&mut program, &mut open, LexerToken::BasicToken(token))
+                }
+            }
             Token {
                 kind: TokenKind::ClosingBracket(character),
                 ..
             } => {
-                let last_stack_value = stack.pop().expect("Unmatched closing bracket (type 3)");
-                if last_stack_value.delimiter == None || last_stack_value.delimiter == Some(';') {
-                    panic!(
-                        "Expected a closing bracket to match {:?} but got {character}",
-                        last_stack_value.delimiter
-                    )
+                let mut last_stack_value = open.pop().unwrap_or_else(statement);
+                if closing_bracket(last_stack_value.delimiter) != Some(character) {
+                    diagnostics.push(errors::CellTailError::new(
+                        &token,
+                        match closing_bracket(last_stack_value.delimiter) {
+                            Some(expected) => format!("Expected a {expected} but got {character}"),
+                            None => format!("Unexpected closing bracket {character}"),
+                        },
+                    ));
+                    open = vec![statement()];
+                    skipping_statement = true;
+                    continue;
                 }
-                stack
-                    .last_mut()
-                    .expect("Unmatched closing bracket (type 2)")
-                    .contents
-                    .push(LexerToken::Group(last_stack_value));
+                last_stack_value.brackets = last_stack_value
+                    .brackets
+                    .map(|(opening, _)| (opening, token.end));
+                push_token(&mut program, &mut open, LexerToken::Group(last_stack_value));
             }
-            k => stack
-                .last_mut()
-                .expect("Unmatched closing parenthesis (type 1)")
-                .contents
-                .push(LexerToken::BasicToken(k)),
+            k => push_token(&mut program, &mut open, LexerToken::BasicToken(k)),
         }
     }
 
-    if stack.len() != 2 {
-        return Err(errors::CellTailError::new(
-            &stack.pop(),
+    match open.as_slice() {
+        [statement] if statement.contents.is_empty() => (),
+        [statement]
+            if statement
+                .contents
+                .iter()
+                .all(|i| i.kind() == Some(TokenKind::Comment)) =>
+        {
+            program.push(LexerToken::Group(statement.clone()))
+        }
+        [statement] => diagnostics.push(errors::CellTailError::new(
+            statement,
+            "Expected a semicolon at the end".to_owned(),
+        )),
+        [.., unclosed] => diagnostics.push(errors::CellTailError::new(
+            unclosed,
             format!(
                 "Missing a closing bracket (type 4) Number required: {}",
-                stack.len() as isize - 1
+                open.len() - 1
             ),
-        ));
+        )),
+        [] => (),
     }
 
-    if stack.len() >= 2 && stack[1].contents.len() > 0 {
-        Err(errors::CellTailError::new(
-            stack.last().unwrap(),
-            "Expected a semicolon at the end".to_owned(),
-        ))?
+    TokenGroup {
+        delimiter: None,
+        brackets: None,
+        contents: program,
     }
-
-    Ok(stack.remove(0))
 }
@@ -0,0 +1,153 @@
+use crate::errors;
+use crate::lexer;
+use crate::parser;
+use crate::runtime::pattern_list::PatternPosition;
+use crate::tokenizer;
+use std::path::{Path, PathBuf};
+
+const PRELUDE: &str = include_str!("prelude.ct");
+pub const MAIN_FILE: errors::FileId = 0;
+
+fn parse_file(
+    source: &errors::SourceFile,
+    file: errors::FileId,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) -> parser::Program {
+    let mut file_diagnostics = vec![];
+    let tokens = tokenizer::tokenize(&source.contents, &mut file_diagnostics);
+    let lexical_tokens = lexer::lex(tokens, &mut file_diagnostics);
+    let mut program = parser::parse(lexical_tokens, &mut file_diagnostics);
+    program.set_file(file);
+
+    diagnostics.extend(file_diagnostics.into_iter().map(|e| e.in_file(file)));
+    program
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+struct Loader<'a> {
+    sources: &'a mut Vec<errors::SourceFile>,
+    import_stack: Vec<PathBuf>,
+    loaded: Vec<PathBuf>,
+    diagnostics: Vec<errors::CellTailError>,
+}
+
+impl Loader<'_> {
+    fn load_imports(&mut self, program: &mut parser::Program, file: errors::FileId) {
+        let directory = Path::new(&self.sources[file].name)
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_default();
+
+        for (import_source, position) in std::mem::take(&mut program.imports) {
+            match self.load_import(&import_source, &position, &directory) {
+                Ok(Some(library)) => program.import_functions(library, &mut self.diagnostics),
+                Ok(None) => (),
+                Err(error) => self.diagnostics.push(error),
+            }
+        }
+    }
+
+    fn load_import(
+        &mut self,
+        import_source: &parser::ImportSource,
+        position: &PatternPosition,
+        directory: &Path,
+    ) -> errors::CellTailResult<Option<parser::Program>> {
+        let (canonical, name) = match import_source {
+            parser::ImportSource::File(import_path) => {
+                let path = directory.join(import_path);
+                (canonical_path(&path), path.to_string_lossy().into_owned())
+            }
+            parser::ImportSource::Bundled(library) => (
+                PathBuf::from(format!("<{library}>")),
+                format!("<{library}>"),
+            ),
+        };
+
+        if let Some(cycle_start) = self.import_stack.iter().position(|i| *i == canonical) {
+            return Err(errors::CellTailError::new(
+                position,
+                format!(
+                    "Import cycle detected: {} -> {}",
+                    self.import_stack[cycle_start..]
+                        .iter()
+                        .map(|i| i.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" -> "),
+                    canonical.display()
+                ),
+            ));
+        }
+        if self.loaded.contains(&canonical) {
+            return Ok(None);
+        }
+
+        let contents = match import_source {
+            parser::ImportSource::File(_) => std::fs::read_to_string(&name).map_err(|e| {
+                errors::CellTailError::new(
+                    position,
+                    format!("Failed to read imported file {name}: {e}"),
+                )
+            })?,
+            parser::ImportSource::Bundled(library) => match library.as_str() {
+                "prelude" => PRELUDE.to_owned(),
+                _ => Err(errors::CellTailError::new(
+                    position,
+                    format!("Unknown bundled library {library:?}, expected \"prelude\""),
+                ))?,
+            },
+        };
+        self.sources.push(errors::SourceFile::new(name, &contents));
+        let library_file = self.sources.len() - 1;
+
+        let mut library = parse_file(
+            &self.sources[library_file],
+            library_file,
+            &mut self.diagnostics,
+        );
+        if let Some(rule) = library.rules.0.first() {
+            return Err(errors::CellTailError::new(
+                &rule.2,
+                "Imported files may only contain functions and imports, not rules".to_owned(),
+            ));
+        }
+        if let Some((_, position)) = library.attribute_definitions.first() {
+            return Err(errors::CellTailError::new(
+                position,
+                "Imported files may only contain functions and imports, not attributes".to_owned(),
+            ));
+        }
+
+        self.import_stack.push(canonical.clone());
+        self.load_imports(&mut library, library_file);
+        self.import_stack.pop();
+        self.loaded.push(canonical);
+
+        Ok(Some(library))
+    }
+}
+
+pub fn load_program(
+    sources: &mut Vec<errors::SourceFile>,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) -> parser::Program {
+    let mut loader = Loader {
+        import_stack: vec![canonical_path(Path::new(&sources[MAIN_FILE].name))],
+        loaded: vec![],
+        diagnostics: vec![],
+        sources,
+    };
+
+    let mut program = parse_file(
+        &loader.sources[MAIN_FILE],
+        MAIN_FILE,
+        &mut loader.diagnostics,
+    );
+    loader.load_imports(&mut program, MAIN_FILE);
+
+    diagnostics.append(&mut loader.diagnostics);
+    program
+}
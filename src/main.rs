@@ -11,66 +11,240 @@ use wasm_bindgen::prelude::*;
 
 mod checker;
 mod errors;
+#[cfg(not(target_arch = "wasm32"))]
+mod formatter;
+#[cfg(not(target_arch = "wasm32"))]
+mod golfer;
 mod interpreter;
 mod lexer;
+mod loader;
 mod parser;
 mod runtime;
+mod shell_tools;
 mod tokenizer;
 #[cfg(target_arch = "wasm32")]
 mod wasm_output;
-mod shell_tools;
 
-fn parse_and_run_code<T: std::io::Write>(
-    code: &Vec<char>,
+fn load_and_check(
+    sources: &mut Vec<errors::SourceFile>,
+    infer_shapes: bool,
+    diagnostics: &mut Vec<errors::CellTailError>,
+) -> Option<parser::Program> {
+    let structure = loader::load_program(sources, diagnostics);
+    let load_failed = errors::has_errors(diagnostics);
+
+    // Statements that failed to parse are left out, the rest is still checked
+    let mut checked = vec![];
+    checker::check_program(&structure, infer_shapes, &mut checked);
+    if load_failed {
+        // Warnings about a partly parsed program are mostly noise
+        checked.retain(|i| !i.is_warning());
+    }
+    diagnostics.append(&mut checked);
+    errors::remove_suppressed(diagnostics, sources);
+
+    (!errors::has_errors(diagnostics)).then_some(structure)
+}
+
+fn run_code<T: std::io::Write>(
+    structure: parser::Program,
     input: Vec<String>,
     output: &mut T,
+    warnings: &mut Vec<errors::CellTailError>,
 ) -> errors::CellTailResult<()> {
-    let tokens = tokenizer::tokenize(code)?;
-    let lexical_tokens = lexer::lex(tokens)?;
-    let structure = parser::parse(lexical_tokens)?;
-
-    checker::check_program(&structure)?;
-
     if structure.attributes.debug {
-        writeln!(output, "{:?}", structure).unwrap();
+        write!(output, "{}", structure).unwrap();
     }
 
-    interpreter::run_program(structure, input, output)
+    interpreter::run_program(structure, input, output, warnings)
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn wasm_parse_run_code(code: &str, input: &str) -> String {
-    let source_code = code.chars().collect();
+    let mut sources = vec![errors::SourceFile::new("<input>".to_owned(), code)];
+    let mut diagnostics = vec![];
 
-    let error = parse_and_run_code(
-        &source_code,
-        vec![input.to_owned()],
-        &mut wasm_output::FunctionWriter::create_stdout(),
-    );
+    if let Some(structure) = load_and_check(&mut sources, false, &mut diagnostics) {
+        let mut warnings = vec![];
+        if let Err(error) = run_code(
+            structure,
+            vec![input.to_owned()],
+            &mut wasm_output::FunctionWriter::create_stdout(),
+            &mut warnings,
+        ) {
+            warnings.push(error);
+        }
+        errors::remove_suppressed(&mut warnings, &sources);
+        diagnostics.append(&mut warnings);
+    }
+
+    errors::to_json(&mut diagnostics, &sources)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn report_diagnostics(
+    diagnostics: &mut [errors::CellTailError],
+    sources: &[errors::SourceFile],
+    json_diagnostics: bool,
+) {
+    if diagnostics.is_empty() {
+        return;
+    }
+
+    if json_diagnostics {
+        eprintln!("{}", errors::to_json(diagnostics, sources));
+    } else {
+        errors::print_all(diagnostics, sources, &mut std::io::stderr());
+    }
+}
+
+// Formats the given files in place, or only reports the files that aren't
+// formatted with --check. Returns the exit code
+#[cfg(not(target_arch = "wasm32"))]
+fn format_files(args: impl Iterator<Item = String>) -> i32 {
+    let mut check = false;
+    let mut files = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown flag {flag}, expected --check");
+                return 2;
+            }
+            _ => files.push(arg),
+        }
+    }
 
-    if let Err(error) = error {
-        error.print(
-            source_code,
-            &mut wasm_output::FunctionWriter::create_stderr(),
-        )
+    let mut exit_code = 0;
+    for filename in files {
+        let contents = fs::read_to_string(&filename).expect("Couldn't read the file");
+        let sources = vec![errors::SourceFile::new(filename.clone(), &contents)];
+        let mut diagnostics = vec![];
+
+        match formatter::format(&sources[0].contents, &mut diagnostics) {
+            Some(formatted) if formatted == contents => (),
+            Some(_) if check => {
+                eprintln!("{filename} is not formatted");
+                exit_code = 1;
+            }
+            Some(formatted) => fs::write(&filename, formatted).expect("Couldn't write the file"),
+            None => {
+                report_diagnostics(&mut diagnostics, &sources, false);
+                exit_code = 1;
+            }
+        }
     }
 
-    "".to_string()
+    exit_code
 }
 
+// Prints the program with as few bytes as possible, and the number of bytes.
+// Returns the exit code
 #[cfg(not(target_arch = "wasm32"))]
-fn main() {
-    let filename = env::args().nth(1).expect("Expected at least one argument");
+fn golf_file(args: impl Iterator<Item = String>) -> i32 {
+    let mut rename_variables = false;
+    let mut files = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--rename" => rename_variables = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown flag {flag}, expected --rename");
+                return 2;
+            }
+            _ => files.push(arg),
+        }
+    }
+    let [filename] = files.as_slice() else {
+        eprintln!("Expected one file to golf");
+        return 2;
+    };
+
     let contents = fs::read_to_string(filename).expect("Couldn't read the file");
-    let contents_chars = contents.chars().collect::<Vec<char>>();
+    let mut sources = vec![errors::SourceFile::new(filename.clone(), &contents)];
+    let mut diagnostics = vec![];
+
+    match golfer::golf(&mut sources, rename_variables, &mut diagnostics) {
+        Some(golfed) => {
+            println!("{golfed}");
+            eprintln!("{} bytes", golfed.len());
+            0
+        }
+        None => {
+            report_diagnostics(&mut diagnostics, &sources, false);
+            1
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    if args.next_if_eq("fmt").is_some() {
+        std::process::exit(format_files(args));
+    }
+    if args.next_if_eq("golf").is_some() {
+        std::process::exit(golf_file(args));
+    }
+
+    let mut json_diagnostics = false;
+    let mut infer_shapes = false;
+    let mut output_mode = None;
+    while let Some(flag) = args.next_if(|i| i.starts_with("--")) {
+        match flag.as_str() {
+            "--error-format=json" => json_diagnostics = true,
+            "--error-format=text" => json_diagnostics = false,
+            "--infer-shapes" => infer_shapes = true,
+            _ if flag.starts_with("--output=") => {
+                let name = &flag["--output=".len()..];
+                output_mode = runtime::attributes::IOFormat::from_output_name(name);
+                if output_mode.is_none() {
+                    eprintln!(
+                        "Unknown output mode {name}, expected one of {}",
+                        runtime::attributes::IOFormat::output_names()
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    std::process::exit(2);
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Unknown flag {flag}, expected --error-format=text, --error-format=json, --infer-shapes or --output=<mode>"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let filename = args.next().expect("Expected at least one argument");
+    let contents = fs::read_to_string(&filename).expect("Couldn't read the file");
+    let mut sources = vec![errors::SourceFile::new(filename, &contents)];
+    let mut diagnostics = vec![];
+
+    let structure = load_and_check(&mut sources, infer_shapes, &mut diagnostics);
+    report_diagnostics(&mut diagnostics, &sources, json_diagnostics);
+    let Some(mut structure) = structure else {
+        std::process::exit(1);
+    };
+    if let Some(output_mode) = output_mode {
+        structure.attributes.output_mode = output_mode;
+    }
 
-    if let Err(b) = parse_and_run_code(
-        &contents_chars,
-        env::args().skip(2).collect(),
+    // Runtime warnings are reported together with the error that ended the run
+    let mut runtime_diagnostics = vec![];
+    let result = run_code(
+        structure,
+        args.collect(),
         &mut std::io::stdout(),
-    ) {
-        b.print(contents_chars, &mut std::io::stderr());
+        &mut runtime_diagnostics,
+    );
+    let failed = result.is_err();
+    runtime_diagnostics.extend(result.err());
+    errors::remove_suppressed(&mut runtime_diagnostics, &sources);
+    report_diagnostics(&mut runtime_diagnostics, &sources, json_diagnostics);
+    if failed {
+        std::process::exit(1);
     }
 }
 
@@ -78,3 +252,179 @@ fn main() {
 fn main() {
     panic::set_hook(Box::new(console_error_panic_hook::hook))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAGMENTS: &[&str] = &[
+        "I",
+        "O",
+        "D",
+        "M",
+        "S",
+        "N",
+        "_",
+        "a",
+        "b",
+        "fn",
+        "for",
+        "in",
+        "mirror",
+        "for x in [1, 'a'] ",
+        "for y in (0..3) ",
+        "for z in (-9223372036854775807..9223372036854775807) ",
+        "for w in (9223372036854775807..0) ",
+        "for v in (0..9999) for u in (0..9999) ",
+        "(0..100000000)",
+        "import",
+        "prelude",
+        "true",
+        "=",
+        ";",
+        ",",
+        ":",
+        "(",
+        ")",
+        "[",
+        "]",
+        "{",
+        "}",
+        ".",
+        "..",
+        "'",
+        "'a'",
+        "'\\u{41}'",
+        "\"",
+        "\"ab\"",
+        "\\",
+        "0",
+        "1",
+        "-1",
+        "0x",
+        "0b12",
+        "1_000",
+        "99999999999999999999999",
+        "+",
+        "-",
+        "*",
+        "/",
+        "%",
+        "&",
+        "|",
+        "^",
+        "**",
+        "@",
+        "#",
+        "# allow: unused-variable",
+        "\n",
+        " ",
+        "é",
+        "$",
+    ];
+
+    // A small deterministic xorshift generator, so failures can be reproduced
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % max as u64) as usize
+        }
+    }
+
+    fn garbage(random: &mut Random) -> String {
+        (0..random.next(40))
+            .map(|_| FRAGMENTS[random.next(FRAGMENTS.len())])
+            .collect()
+    }
+
+    // Inserts, removes and replaces characters of a working program
+    fn mutate(program: &str, random: &mut Random) -> String {
+        let mut characters: Vec<char> = program.chars().collect();
+        for _ in 0..=random.next(4) {
+            let position = random.next(characters.len() + 1);
+            let (removed, inserted) = match random.next(3) {
+                0 => (0, garbage(random)),
+                1 => (3, String::new()),
+                _ => (1, FRAGMENTS[random.next(FRAGMENTS.len())].to_owned()),
+            };
+            let end = (position + removed).min(characters.len());
+            characters.splice(position..end, inserted.chars());
+        }
+        characters.into_iter().collect()
+    }
+
+    fn assert_total(code: &str) {
+        let mut sources = vec![errors::SourceFile::new("<garbage>".to_owned(), code)];
+        let mut diagnostics = vec![];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            load_and_check(&mut sources, true, &mut diagnostics)
+        }));
+        assert!(result.is_ok(), "The front end panicked on {code:?}");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&errors::to_json(&mut diagnostics, &sources)).unwrap();
+        for diagnostic in json.as_array().unwrap() {
+            assert!(
+                !diagnostic["start"].is_null(),
+                "{:?} has no location in {code:?}",
+                diagnostic["message"]
+            );
+        }
+    }
+
+    #[test]
+    fn front_end_is_total() {
+        let mut random = Random(0x5eed);
+        let examples: Vec<String> = fs::read_dir("examples")
+            .unwrap()
+            .map(|i| fs::read_to_string(i.unwrap().path()).unwrap())
+            .collect();
+
+        for _ in 0..2000 {
+            assert_total(&garbage(&mut random));
+        }
+        for example in &examples {
+            for _ in 0..200 {
+                assert_total(&mutate(example, &mut random));
+            }
+        }
+    }
+
+    // Mutated programs that don't load are skipped, the examples must load
+    fn assert_round_trip(name: &str, code: &str, mutated: bool) {
+        let mut sources = vec![errors::SourceFile::new(name.to_owned(), code)];
+        let mut diagnostics = vec![];
+        let Some(program) = load_and_check(&mut sources, false, &mut diagnostics) else {
+            assert!(mutated, "{name} doesn't load: {diagnostics:?}");
+            return;
+        };
+        let printed = program.to_string();
+
+        let mut printed_sources = vec![errors::SourceFile::new(name.to_owned(), &printed)];
+        let mut diagnostics = vec![];
+        let reparsed = load_and_check(&mut printed_sources, false, &mut diagnostics);
+        assert!(
+            reparsed.is_some_and(|reparsed| golfer::same_program(&program, &reparsed)),
+            "{name} was printed as\n{printed}\n{diagnostics:?}"
+        );
+    }
+
+    #[test]
+    fn printed_programs_parse_the_same() {
+        let mut random = Random(0x5eed);
+        for path in fs::read_dir("examples").unwrap() {
+            let path = path.unwrap().path();
+            let example = fs::read_to_string(&path).unwrap();
+            let name = path.to_string_lossy();
+
+            assert_round_trip(&name, &example, false);
+            for _ in 0..200 {
+                assert_round_trip(&name, &mutate(&example, &mut random), true);
+            }
+        }
+    }
+}
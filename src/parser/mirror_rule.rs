@@ -0,0 +1,74 @@
+use crate::errors;
+use crate::runtime::expression::Expression;
+use crate::runtime::literal::Literal;
+use crate::runtime::pattern::Pattern;
+use crate::runtime::pattern_list::PatternPosition;
+
+fn mirror_pattern(
+    pattern: &Pattern,
+    position: &PatternPosition,
+) -> errors::CellTailResult<Pattern> {
+    match pattern {
+        Pattern::Tuple(parts) if parts.len() == 3 => Ok(Pattern::Tuple(vec![
+            parts[2].clone(),
+            parts[1].clone(),
+            parts[0].clone(),
+        ])),
+        Pattern::Or(parts, position) => Ok(Pattern::Or(
+            parts
+                .iter()
+                .map(|part| mirror_pattern(part, position))
+                .collect::<errors::CellTailResult<_>>()?,
+            position.clone(),
+        )),
+        Pattern::And(parts) => Ok(Pattern::And(
+            parts
+                .iter()
+                .map(|part| mirror_pattern(part, position))
+                .collect::<errors::CellTailResult<_>>()?,
+        )),
+        Pattern::Any => Ok(Pattern::Any),
+        _ => Err(errors::CellTailError::new(
+            position,
+            "Can't mirror this rule, the pattern must be a 3 tuple (left, center, right)"
+                .to_owned(),
+        )),
+    }
+}
+
+fn mirror_expression(
+    expression: &Expression,
+    position: &PatternPosition,
+) -> errors::CellTailResult<Expression> {
+    match expression {
+        Expression::Tuple(parts) if parts.len() == 3 => Ok(Expression::Tuple(vec![
+            parts[2].clone(),
+            parts[1].clone(),
+            parts[0].clone(),
+        ])),
+        Expression::Literal(Literal::Tuple(parts)) if parts.len() == 3 => {
+            Ok(Expression::Literal(Literal::Tuple(vec![
+                parts[2].clone(),
+                parts[1].clone(),
+                parts[0].clone(),
+            ])))
+        }
+        _ => Err(errors::CellTailError::new(
+            position,
+            "Can't mirror this rule, the result must be written as a 3 tuple (left, down, right)"
+                .to_owned(),
+        )),
+    }
+}
+
+pub(super) fn mirror_rule(
+    pattern: &Pattern,
+    expression: &Expression,
+    pattern_position: &PatternPosition,
+    expression_position: &PatternPosition,
+) -> errors::CellTailResult<(Pattern, Expression)> {
+    Ok((
+        mirror_pattern(pattern, pattern_position)?,
+        mirror_expression(expression, expression_position)?,
+    ))
+}
@@ -6,17 +6,29 @@ use crate::runtime::pattern::Pattern;
 use crate::runtime::pattern_list::{PatternList, PatternPosition};
 use crate::tokenizer::{Token, TokenKind};
 use std::collections::HashMap;
+use std::fmt;
 
+mod mirror_rule;
 mod parse_array;
 mod parse_attribute;
 mod parse_expression;
+mod parse_number;
 mod parse_pattern;
+mod parse_template;
+
+#[derive(Debug)]
+pub enum ImportSource {
+    File(String),
+    Bundled(String),
+}
 
 #[derive(Debug)]
 pub struct Program {
     pub functions: HashMap<String, PatternList>,
     pub rules: PatternList,
     pub attributes: attributes::Attributes,
+    pub imports: Vec<(ImportSource, PatternPosition)>,
+    pub attribute_definitions: Vec<(&'static str, PatternPosition)>,
 }
 
 impl Program {
@@ -25,6 +37,48 @@ impl Program {
             functions: HashMap::new(),
             rules: PatternList(Vec::new()),
             attributes: attributes::Attributes::new(),
+            imports: Vec::new(),
+            attribute_definitions: Vec::new(),
+        }
+    }
+
+    pub fn set_file(&mut self, file: errors::FileId) {
+        for function in self.functions.values_mut() {
+            for rule in &mut function.0 {
+                rule.2.set_file(file);
+            }
+        }
+        for rule in &mut self.rules.0 {
+            rule.2.set_file(file);
+        }
+        for import in &mut self.imports {
+            import.1.set_file(file);
+        }
+        for attribute in &mut self.attribute_definitions {
+            attribute.1.set_file(file);
+        }
+    }
+
+    pub fn import_functions(
+        &mut self,
+        library: Program,
+        diagnostics: &mut Vec<errors::CellTailError>,
+    ) {
+        for (function_name, function) in library.functions {
+            if let Some(existing) = self.functions.get(&function_name) {
+                diagnostics.push(
+                    errors::CellTailError::new(
+                        &existing.0[0].2,
+                        format!("Function {function_name:?} is defined more than once"),
+                    )
+                    .with_related_note(
+                        &function.0[0].2,
+                        "It is also defined by this import".to_owned(),
+                    ),
+                );
+                continue;
+            }
+            self.functions.insert(function_name, function);
         }
     }
 
@@ -46,85 +100,213 @@ impl Program {
     }
 }
 
-pub fn parse(input: TokenGroup) -> errors::CellTailResult<Program> {
-    let mut out = Program::new();
-    for statement in input.contents {
-        let statement_position = PatternPosition::new(&statement);
+// Imported functions are part of the program, so the imports are left out
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.attributes, self.rules)?;
 
-        if let LexerToken::Group(group) = statement {
-            if group.contains(TokenKind::Equals) {
-                parse_attribute::parse_attribute(group, &mut out.attributes)?;
-            } else if let Some((pattern, _operator, expression)) =
-                group.split_first(TokenKind::Colon)
-            {
-                if let LexerToken::BasicToken(
-                    t @ Token {
-                        kind: TokenKind::Identifier,
+        let mut names: Vec<_> = self.functions.keys().collect();
+        names.sort();
+        for name in names {
+            self.functions[name].write_rules(f, &format!("fn {name} "))?;
+        }
+        Ok(())
+    }
+}
+
+fn strip_rule_modifier(pattern: &TokenGroup, modifier: &str) -> Option<TokenGroup> {
+    match pattern.contents.as_slice() {
+        [LexerToken::BasicToken(Token {
+            kind: TokenKind::Identifier,
+            value,
+            ..
+        }), rest @ ..]
+            if value == modifier
+                && !matches!(
+                    rest.first(),
+                    None | Some(LexerToken::BasicToken(Token {
+                        kind: TokenKind::Comma | TokenKind::Operator(_) | TokenKind::Elipsis,
                         ..
-                    },
-                ) = &pattern.contents[0]
-                {
-                    if t.value == "fn" {
-                        let function_name = if let LexerToken::BasicToken(
-                            t @ Token {
-                                kind: TokenKind::Identifier,
-                                ..
-                            },
-                        ) = &pattern.contents[1]
-                        {
-                            t.value.clone()
-                        } else {
-                            return Err(errors::CellTailError::new(
-                                &pattern,
-                                "Expected a function name".to_owned(),
-                            ));
-                        };
-                        out.add_function_rule(
-                            function_name,
-                            (
-                                errors::fallback_position(
-                                    parse_pattern::parse_as_pattern(TokenGroup {
-                                        delimiter: None,
-                                        contents: pattern.contents[2..].to_vec(),
-                                    }),
-                                    &statement_position,
-                                )?,
-                                errors::fallback_position(
-                                    parse_expression::parse_as_expression(expression),
-                                    &statement_position,
-                                )?,
-                                PatternPosition::new(&statement_position),
-                            ),
-                        );
-
-                        continue;
-                    }
-                }
+                    }))
+                ) =>
+        {
+            Some(TokenGroup {
+                delimiter: pattern.delimiter,
+                brackets: None,
+                contents: rest.to_vec(),
+            })
+        }
+        _ => None,
+    }
+}
 
-                out.add_rule((
-                    errors::fallback_position(
-                        parse_pattern::parse_as_pattern(pattern),
-                        &statement_position,
-                    )?,
-                    errors::fallback_position(
-                        parse_expression::parse_as_expression(expression),
-                        &statement_position,
-                    )?,
-                    PatternPosition::new(&statement_position),
-                ))
-            } else {
+type RuleDefinition = (Option<String>, Pattern, Expression);
+
+fn parse_rule(
+    pattern: TokenGroup,
+    expression: TokenGroup,
+) -> errors::CellTailResult<Vec<RuleDefinition>> {
+    if let Some(rest) = strip_rule_modifier(&pattern, "for") {
+        let template = parse_template::parse_template(rest)?;
+        let position = PatternPosition::new(&pattern);
+
+        let body = parse_rule(template.body, expression)?;
+        if template.values.len() * body.len() > parse_template::MAX_TEMPLATE_RULES {
+            Err(errors::CellTailError::new(
+                &position,
+                format!(
+                    "Template generates more than {} rules",
+                    parse_template::MAX_TEMPLATE_RULES
+                ),
+            ))?
+        }
+        return Ok(template
+            .values
+            .iter()
+            .flat_map(|value| {
+                body.iter().map(|(function_name, pattern, expression)| {
+                    (
+                        function_name.clone(),
+                        parse_template::substitute_pattern(pattern, &template.name, value),
+                        parse_template::substitute_expression(expression, &template.name, value),
+                    )
+                })
+            })
+            .collect());
+    }
+
+    if let Some(rest) = strip_rule_modifier(&pattern, "fn") {
+        let function_name = if let LexerToken::BasicToken(
+            t @ Token {
+                kind: TokenKind::Identifier,
+                ..
+            },
+        ) = &rest.contents[0]
+        {
+            t.value.clone()
+        } else {
+            return Err(errors::CellTailError::new(
+                &pattern,
+                "Expected a function name".to_owned(),
+            ));
+        };
+
+        return Ok(vec![(
+            Some(function_name),
+            parse_pattern::parse_as_pattern(TokenGroup {
+                delimiter: None,
+                brackets: None,
+                contents: rest.contents[1..].to_vec(),
+            })?,
+            parse_expression::parse_as_expression(expression)?,
+        )]);
+    }
+
+    if let Some(rest) = strip_rule_modifier(&pattern, "mirror") {
+        let pattern_position = PatternPosition::new(&rest);
+        let expression_position = PatternPosition::new(&expression);
+        let mut rules = vec![];
+        for (function_name, pattern, expression) in parse_rule(rest, expression)? {
+            let (mirrored_pattern, mirrored_expression) = mirror_rule::mirror_rule(
+                &pattern,
+                &expression,
+                &pattern_position,
+                &expression_position,
+            )?;
+            // A symmetric rule is its own mirror image
+            let symmetric = mirrored_pattern.to_string() == pattern.to_string()
+                && mirrored_expression.to_string() == expression.to_string();
+            rules.push((function_name.clone(), pattern, expression));
+            if !symmetric {
+                rules.push((function_name, mirrored_pattern, mirrored_expression));
+            }
+        }
+        return Ok(rules);
+    }
+
+    Ok(vec![(
+        None,
+        parse_pattern::parse_as_pattern(pattern)?,
+        parse_expression::parse_as_expression(expression)?,
+    )])
+}
+
+fn parse_statement(statement: LexerToken, out: &mut Program) -> errors::CellTailResult<()> {
+    let statement_position = PatternPosition::new(&statement);
+
+    if let LexerToken::Group(group) = statement {
+        if let [LexerToken::BasicToken(Token {
+            kind: TokenKind::Identifier,
+            value: keyword,
+            ..
+        }), LexerToken::BasicToken(source)] = group.contents.as_slice()
+        {
+            if keyword != "import" {
                 return Err(errors::CellTailError::new(
                     &group,
-                    "Missing : seperating pattern from expression".to_owned(),
-                ));
+                    format!("Unexpected statement {keyword:?}"),
+                )
+                .with_suggestion("import".to_owned()));
+            }
+            let source = match source.kind {
+                TokenKind::String => ImportSource::File(source.value.clone()),
+                TokenKind::Identifier => ImportSource::Bundled(source.value.clone()),
+                _ => {
+                    return Err(errors::CellTailError::new(
+                        source,
+                        "Expected a file name like \"lib.ct\" or the name of a bundled library like prelude".to_owned(),
+                    ))
+                }
+            };
+            out.imports.push((source, statement_position));
+        } else if group.contains(TokenKind::Equals) {
+            let name = parse_attribute::parse_attribute(group, &mut out.attributes)?;
+            out.attribute_definitions.push((name, statement_position));
+        } else if let Some((pattern, _operator, expression)) = group.split_first(TokenKind::Colon) {
+            for (function_name, pattern, expression) in
+                errors::fallback_position(parse_rule(pattern, expression), &statement_position)?
+            {
+                let rule = (
+                    pattern,
+                    expression,
+                    PatternPosition::new(&statement_position),
+                );
+                match function_name {
+                    Some(function_name) => out.add_function_rule(function_name, rule),
+                    None => out.add_rule(rule),
+                }
             }
         } else {
             return Err(errors::CellTailError::new(
-                &statement,
-                "Invalid top-level statement".to_owned(),
+                &group,
+                "Missing : seperating pattern from expression".to_owned(),
             ));
         }
+    } else {
+        return Err(errors::CellTailError::new(
+            &statement,
+            "Invalid top-level statement".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn parse(input: TokenGroup, diagnostics: &mut Vec<errors::CellTailError>) -> Program {
+    let mut out = Program::new();
+    for statement in input.contents {
+        let position = PatternPosition::new(&statement);
+        match &statement {
+            // Empty statements like ;; don't do anything
+            LexerToken::Group(group) if group.contents.is_empty() => continue,
+            _ => (),
+        }
+        if let Err(error) =
+            errors::fallback_position(parse_statement(statement, &mut out), &position)
+        {
+            diagnostics.push(error);
+        }
     }
 
-    return Ok(out);
+    out
 }
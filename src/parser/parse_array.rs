@@ -15,7 +15,7 @@ where
     if input.delimiter != Some('[') {
         return Err(errors::CellTailError::new(
             &input,
-            format!("Expected a list to be delimited by []"),
+            "Expected a list to be delimited by []".to_string(),
         ));
     }
 
@@ -1,54 +1,79 @@
-fn parse_as_number(input: &TokenGroup) -> errors::CellTailResult<isize> {
-    if input.contents.len() == 2 {
-        if let (
-            LexerToken::BasicToken(Token {
-                kind: TokenKind::Operator('-'),
-                ..
-            }),
-            LexerToken::BasicToken(Token {
-                kind: TokenKind::Number,
+fn attribute_name(name: &str) -> Option<&'static str> {
+    match name {
+        "I" | "Input" => Some("Input"),
+        "O" | "Output" => Some("Output"),
+        "D" | "Debug" => Some("Debug"),
+        "M" | "Max" | "MaxIterations" => Some("Max"),
+        "S" | "Strict" => Some("Strict"),
+        _ => None,
+    }
+}
+
+fn parse_flag(name: &str, value: &TokenGroup) -> errors::CellTailResult<bool> {
+    match value.contents.as_slice() {
+        [LexerToken::BasicToken(Token {
+            kind: TokenKind::Identifier,
+            value: val,
+            ..
+        })] => match val.to_lowercase().as_str() {
+            "t" | "y" | "true" | "yes" => Ok(true),
+            "n" | "f" | "no" | "false" => Ok(false),
+            _ => Err(errors::CellTailError::new(
                 value,
-                ..
-            }),
-        ) = (&input.contents[0], &input.contents[1])
-        {
-            Ok(-value.parse().map_err(|k| {
-                errors::CellTailError::new(
-                    input,
-                    format!("Failed to parse negative number literal: {k:?}"),
-                )
-            })?)
-        } else {
-            Err(errors::CellTailError::new(
-                input,
-                format!("Failure parsing as negative number literal, expected 2 tokens with the left being '-` but got a {:?}", input.contents[0]),
-            ))
-        }
-    } else if input.contents.len() == 1 {
-        if let LexerToken::BasicToken(Token {
-            kind: TokenKind::Number,
+                format!("Invalid {name} mode, must be one of \"true\" or \"false\""),
+            )
+            .with_closest(val, ["true", "false", "yes", "no"])),
+        },
+        _ => Err(errors::CellTailError::new(
             value,
-            ..
-        }) = &input.contents[0]
-        {
-            value.parse().map_err(|k| {
-                errors::CellTailError::new(
-                    input,
-                    format!("Failed to parse positive number literal: {k:?}"),
-                )
-            })
-        } else {
-            Err(errors::CellTailError::new(
-                input,
-                "Invalid positive number literal".to_owned(),
-            ))
+            format!("Invalid type for property {name:?}, note: must be token, no parenthesis allowed here"),
+        )),
+    }
+}
+
+// The comma separated values of a constant input, an empty one like in
+// 1,,2 is located at the commas around it
+fn split_input_values(value: &TokenGroup) -> errors::CellTailResult<Vec<TokenGroup>> {
+    let mut items = vec![];
+    let mut item = vec![];
+    let mut previous_comma: Option<&Token> = None;
+    for token in &value.contents {
+        let comma = match token {
+            LexerToken::BasicToken(comma) if comma.kind == TokenKind::Comma => comma,
+            _ => {
+                item.push(token.clone());
+                continue;
+            }
+        };
+        if item.is_empty() {
+            let start = previous_comma.map_or(comma.start, |i| i.start);
+            return Err(empty_input_value(start, comma.end));
         }
-    } else {
-        Err(errors::CellTailError::new(
-            input,
-            format!("Empty or too long number literal: {:?}", input.contents),
-        ))
+        items.push(TokenGroup {
+            delimiter: None,
+            brackets: None,
+            contents: std::mem::take(&mut item),
+        });
+        previous_comma = Some(comma);
     }
+    match previous_comma {
+        Some(comma) if item.is_empty() => Err(empty_input_value(comma.start, comma.end)),
+        _ => {
+            items.push(TokenGroup {
+                delimiter: None,
+                brackets: None,
+                contents: item,
+            });
+            Ok(items)
+        }
+    }
+}
+
+fn empty_input_value(start: usize, end: usize) -> errors::CellTailError {
+    errors::CellTailError::new(
+        &errors::RangeError(start, end),
+        "Expected a value between commas in Input".to_owned(),
+    )
 }
 
 fn parse_single_attribute(
@@ -56,14 +81,15 @@ fn parse_single_attribute(
     value: TokenGroup,
     attrs: &mut attributes::Attributes,
 ) -> errors::CellTailResult<()> {
-    match name {
-        "I" | "Input" => {
+    match attribute_name(name) {
+        Some("Input") => {
             if value.contains(TokenKind::Comma) {
-                let result = value.split_all(TokenKind::Comma).iter().map(
-                    parse_as_number
+                let result = split_input_values(&value)?.into_iter().map(
+                    |i| parse_as_constant(i, "Input values")
                 ).collect::<errors::CellTailResult<Vec<_>>>()?;
 
-                Ok(attrs.input_mode = attributes::InputSource::Constant(result))
+                attrs.input_mode = attributes::InputSource::Constant(result);
+                Ok(())
             } else if let [
                 LexerToken::BasicToken(Token{
                 kind: TokenKind::Identifier,
@@ -78,13 +104,24 @@ fn parse_single_attribute(
                 let input_format = match input_format.to_uppercase().as_str() {
                     "N" | "NUMBERS" | "NRS" => attributes::IOFormat::Numbers,
                     "C" | "CHARACTERS" | "CHARS" => attributes::IOFormat::Characters,
-                    _ => Err(errors::CellTailError::new(&value, "Invalid value for input format, expected one of 'NUMBERS' or 'CHARS'".to_owned()))?
+                    "J" | "JSON" => attributes::IOFormat::Json,
+                    "L" | "LINES" => attributes::IOFormat::Lines,
+                    "B" | "BYTES" => attributes::IOFormat::Bytes,
+                    _ => Err(errors::CellTailError::new(&value, "Invalid value for input format, expected one of 'NUMBERS', 'CHARS', 'JSON', 'LINES' or 'BYTES'".to_owned())
+                        .with_closest(input_format, ["NUMBERS", "CHARS", "CHARACTERS", "JSON", "LINES", "BYTES"]))?
                 };
 
                 match input_type.to_uppercase().as_str() {
-                    "I" | "STDIN" => Ok(attrs.input_mode = attributes::InputSource::StdIn(input_format)),
-                    "C" | "CMD" | "COMMANDLINEARGUMENTS" | "ARGS" | "ARGV" | "A" => Ok(attrs.input_mode = attributes::InputSource::Arg(input_format)),
-                    _ => Err(errors::CellTailError::new(&value, "Invalid value for input mode, expected one of 'STDIN', 'CMD'".to_owned()))
+                    "I" | "STDIN" => {
+                        attrs.input_mode = attributes::InputSource::StdIn(input_format);
+                        Ok(())
+                    },
+                    "C" | "CMD" | "COMMANDLINEARGUMENTS" | "ARGS" | "ARGV" | "A" => {
+                        attrs.input_mode = attributes::InputSource::Arg(input_format);
+                        Ok(())
+                    },
+                    _ => Err(errors::CellTailError::new(&value, "Invalid value for input mode, expected one of 'STDIN', 'CMD'".to_owned())
+                        .with_closest(input_type, ["STDIN", "CMD", "ARGS", "ARGV"]))
                 }
             } else if let [
                 LexerToken::BasicToken(Token{
@@ -93,17 +130,21 @@ fn parse_single_attribute(
                     ..
                 })
             ] = value.contents.as_slice() {
-                Ok(attrs.input_mode = attributes::InputSource::Constant(input_format.chars().map(|i| i as u32 as isize).collect()))
+                attrs.input_mode = attributes::InputSource::Constant(input_format.chars().map(|i| Literal::Number(i as u32 as isize)).collect());
+                Ok(())
             }
             else {
-                if let Ok(number) = parse_as_number(&value) {
-                    Ok(attrs.input_mode = attributes::InputSource::Constant(vec![number]))
-                } else {
-                    Err(errors::CellTailError::new(&value, "Invalid attribute value for attribute \"input\", expected 2 words or a comma seperated list of numbers".to_owned()))
+                match parse_as_expression(value.clone()) {
+                    Ok(_) => {
+                        attrs.input_mode = attributes::InputSource::Constant(vec![parse_as_constant(value, "Input values")?]);
+                        Ok(())
+                    }
+                    Err(e) if value.contents.len() <= 2 && value.contains(TokenKind::Number) => Err(e),
+                    Err(_) => Err(errors::CellTailError::new(&value, "Invalid attribute value for attribute \"input\", expected 2 words or a comma seperated list of values".to_owned()))
                 }
             }
         }
-        "O" | "Output" => {
+        Some("Output") => {
             if value.contents.len() != 1 {
                 return Err(errors::CellTailError::new(&value, "Invalid length for property \"output\"".to_owned()))
             }
@@ -114,49 +155,46 @@ fn parse_single_attribute(
                     value: val,
                     ..
                 }) => {
-                   match val.to_lowercase().as_str() {
-                        "c" | "chars" | "characters"  => Ok(attrs.output_mode = attributes::IOFormat::Characters),
-                        "n" | "d" | "numbers" | "decimal" => Ok(attrs.output_mode = attributes::IOFormat::Numbers),
-                        _ => Err(errors::CellTailError::new(&value, "Invalid output mode, must be one of \"characters\" or \"numbers\"".to_owned()))
-                    }
+                    attrs.output_mode = attributes::IOFormat::from_output_name(val).ok_or_else(|| {
+                        errors::CellTailError::new(&value, format!("Invalid output mode, must be one of {}", attributes::IOFormat::output_names().map(|i| format!("{i:?}")).collect::<Vec<_>>().join(", ")))
+                            .with_closest(val, attributes::IOFormat::output_spellings())
+                    })?;
+                    Ok(())
                 }
                 _ => Err(errors::CellTailError::new(&value, "Invalid type for property \"output\", note: must be token, no parenthesis allowed here".to_owned()))
             }
         }
-        "D" | "Debug" => {
-            match &value.contents[0] {
-                LexerToken::BasicToken(Token {
-                    kind: TokenKind::Identifier,
-                    value: val,
-                    ..
-                }) => {
-                   match val.to_lowercase().as_str() {
-                        "t" | "y" | "true" | "yes"  => Ok(attrs.debug = true),
-                        "n" | "f" | "no" | "false" => Ok(attrs.debug = false),
-                        _ => Err(errors::CellTailError::new(&value, "Invalid debug mode, must be one of \"characters\" or \"numbers\"".to_owned()))
-                    }
-                }
-                _ => Err(errors::CellTailError::new(&value, "Invalid type for property \"debug\", note: must be token, no parenthesis allowed here".to_owned()))
-            }
+        Some("Debug") => {
+            attrs.debug = parse_flag("debug", &value)?;
+            Ok(())
+        }
+        Some("Strict") => {
+            attrs.strict = parse_flag("strict", &value)?;
+            Ok(())
         }
-        "M" | "Max" | "MaxIterations" => {
-            Ok(attrs.max_iterations = Some(parse_as_number(&value)?))
+        Some("Max") => {
+            attrs.max_iterations = Some(parse_as_number(&value)?);
+            Ok(())
         },
-        m => {
-            Err(errors::CellTailError::new(&value, format!("Unexpected property name {}, expected one of 'Input', 'I', 'Output', 'O', 'Debug', 'D'", m)))
+        _ => {
+            Err(errors::CellTailError::new(&value, format!("Unexpected property name {}, expected one of 'Input', 'I', 'Output', 'O', 'Debug', 'D', 'Max', 'M', 'Strict', 'S'", name))
+                .with_closest(name, ["Input", "Output", "Debug", "Max", "MaxIterations", "Strict"]))
         }
     }
 }
 
 use crate::errors;
 use crate::lexer::{LexerToken, TokenGroup};
+use crate::parser::parse_expression::{parse_as_constant, parse_as_expression};
+use crate::parser::parse_number::parse_as_number;
 use crate::runtime::attributes;
+use crate::runtime::literal::Literal;
 use crate::tokenizer::{Token, TokenKind};
 
 pub(super) fn parse_attribute(
     input: TokenGroup,
     attributes: &mut attributes::Attributes,
-) -> errors::CellTailResult<()> {
+) -> errors::CellTailResult<&'static str> {
     if let Some((name, _, value)) = input.split_first(TokenKind::Equals) {
         if name.contents.len() != 1 {
             Err(errors::CellTailError::new(
@@ -171,7 +209,8 @@ pub(super) fn parse_attribute(
             ..
         }) = &name.contents[0]
         {
-            parse_single_attribute(name, value, attributes)
+            parse_single_attribute(name, value, attributes)?;
+            Ok(attribute_name(name).unwrap_or_default())
         } else {
             Err(errors::CellTailError::new(
                 &input,
@@ -1,9 +1,12 @@
 use crate::errors;
 use crate::lexer::{LexerToken, TokenGroup};
 use crate::parser::parse_array::parse_array;
+use crate::parser::parse_number::parse_number;
 use crate::runtime::expression::{BinaryOperator, Expression, UnaryOperator};
 use crate::runtime::literal::Literal;
+use crate::runtime::pattern_list::PatternPosition;
 use crate::tokenizer::{Token, TokenKind};
+use std::collections::HashMap;
 
 pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<Expression> {
     if input.delimiter == Some('[') {
@@ -15,7 +18,8 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
         );
     }
 
-    if !(input.delimiter == Some('(') || input.delimiter == Some(';') || input.delimiter == None) {
+    if !(input.delimiter == Some('(') || input.delimiter == Some(';') || input.delimiter.is_none())
+    {
         return Err(errors::CellTailError::new(
             &input,
             format!("Unexpected input delimiter: {:?}", input.delimiter),
@@ -24,11 +28,12 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
     if input.contents.len() == 1 {
         return Ok(match &input.contents[0] {
             LexerToken::Group(group) => parse_as_expression(group.clone())?,
-            LexerToken::BasicToken(Token {
-                kind: TokenKind::Number,
-                value,
-                ..
-            }) => Expression::Literal(Literal::Number(value.parse().unwrap())),
+            LexerToken::BasicToken(
+                token @ Token {
+                    kind: TokenKind::Number,
+                    ..
+                },
+            ) => Expression::Literal(Literal::Number(parse_number(token)?)),
             LexerToken::BasicToken(Token {
                 kind: TokenKind::Identifier,
                 value,
@@ -37,15 +42,19 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
                 "N" => Expression::Literal(Literal::Null),
                 "_" => Err(errors::CellTailError::new(
                     &input.contents[0],
-                    "'_' is not a valid variable name\nHelp: _ indicates discarding  a value. Thus there can never be a value assigned to _.".to_owned(),
-                ))?,
-                _ => Expression::Variable(value.clone()),
+                    "'_' is not a valid variable name".to_owned(),
+                )
+                .with_note("_ indicates discarding  a value. Thus there can never be a value assigned to _.".to_owned()))?,
+                _ => Expression::Variable(
+                    value.clone(),
+                    PatternPosition::new(&input.contents[0]),
+                ),
             },
             LexerToken::BasicToken(Token {
                 kind: TokenKind::String,
                 value,
                 ..
-            }) => Expression::Literal(Literal::new_string_literal(value.clone().as_bytes())),
+            }) => Expression::Literal(Literal::new_string_literal(value)),
             t => {
                 return Err(errors::CellTailError::new(
                     &input,
@@ -55,7 +64,9 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
         });
     }
 
-    if input.contains(TokenKind::Comma) || (input.delimiter == Some('(') && input.contents.len() == 0) {
+    if input.contains(TokenKind::Comma)
+        || (input.delimiter == Some('(') && input.contents.is_empty())
+    {
         return Ok(Expression::Tuple(
             input
                 .split_all(TokenKind::Comma)
@@ -76,6 +87,7 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
                     operator.1,
                     Box::new(parse_as_expression(TokenGroup {
                         delimiter: None,
+                        brackets: None,
                         contents: input.contents[1..].to_vec(),
                     })?),
                 ));
@@ -113,8 +125,10 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
                 value.clone(),
                 Box::new(parse_as_expression(TokenGroup {
                     delimiter: None,
+                    brackets: None,
                     contents: vec![input.contents[1].clone()],
                 })?),
+                PatternPosition::new(&input),
             ));
         }
     }
@@ -124,3 +138,17 @@ pub(super) fn parse_as_expression(input: TokenGroup) -> errors::CellTailResult<E
         format!("Invalid expression {input:?}"),
     ))
 }
+
+// Parses an expression that can be evaluated before the program runs, what
+// names the values in the error message
+pub(super) fn parse_as_constant(input: TokenGroup, what: &str) -> errors::CellTailResult<Literal> {
+    let expression = parse_as_expression(input.clone())?;
+    if expression.is_constant() {
+        expression.evaluate(&HashMap::new(), &HashMap::new(), true, &mut vec![])
+    } else {
+        Err(errors::CellTailError::new(
+            &input,
+            format!("{what} must be constants"),
+        ))
+    }
+}
@@ -0,0 +1,59 @@
+use crate::errors;
+use crate::lexer::{LexerToken, TokenGroup};
+use crate::tokenizer::{split_number_prefix, Token, TokenKind};
+
+pub(super) fn parse_number(token: &Token) -> errors::CellTailResult<isize> {
+    let (radix, digits) = split_number_prefix(&token.value);
+
+    isize::from_str_radix(&digits.replace('_', ""), radix).map_err(|k| {
+        errors::CellTailError::new(
+            token,
+            format!("Failed to parse number literal {:?}: {k}", token.value),
+        )
+    })
+}
+
+pub(super) fn parse_as_number(input: &TokenGroup) -> errors::CellTailResult<isize> {
+    if input.contents.len() == 2 {
+        if let (
+            LexerToken::BasicToken(Token {
+                kind: TokenKind::Operator('-'),
+                ..
+            }),
+            LexerToken::BasicToken(
+                token @ Token {
+                    kind: TokenKind::Number,
+                    ..
+                },
+            ),
+        ) = (&input.contents[0], &input.contents[1])
+        {
+            Ok(-parse_number(token)?)
+        } else {
+            Err(errors::CellTailError::new(
+                input,
+                format!("Failure parsing as negative number literal, expected 2 tokens with the left being '-` but got a {:?}", input.contents[0]),
+            ))
+        }
+    } else if input.contents.len() == 1 {
+        if let LexerToken::BasicToken(
+            token @ Token {
+                kind: TokenKind::Number,
+                ..
+            },
+        ) = &input.contents[0]
+        {
+            parse_number(token)
+        } else {
+            Err(errors::CellTailError::new(
+                input,
+                "Invalid positive number literal".to_owned(),
+            ))
+        }
+    } else {
+        Err(errors::CellTailError::new(
+            input,
+            format!("Empty or too long number literal: {:?}", input.contents),
+        ))
+    }
+}
@@ -2,8 +2,10 @@ use crate::errors;
 use crate::lexer::{LexerToken, TokenGroup};
 use crate::parser::parse_array::parse_array;
 use crate::parser::parse_expression;
+use crate::parser::parse_number::parse_number;
 use crate::runtime::literal::Literal;
 use crate::runtime::pattern::Pattern;
+use crate::runtime::pattern_list::PatternPosition;
 use crate::tokenizer::{Token, TokenKind};
 
 pub(super) fn parse_as_pattern(input: TokenGroup) -> errors::CellTailResult<Pattern> {
@@ -18,25 +20,28 @@ pub(super) fn parse_as_pattern(input: TokenGroup) -> errors::CellTailResult<Patt
     if input.contents.len() == 1 {
         return Ok(match &input.contents[0] {
             LexerToken::Group(group) => parse_as_pattern(group.clone())?,
-            LexerToken::BasicToken(Token {
-                kind: TokenKind::Number,
-                value,
-                ..
-            }) => Pattern::Literal(Literal::Number(value.parse().unwrap())),
-            LexerToken::BasicToken(Token {
-                kind: TokenKind::Identifier,
-                value: v,
-                ..
-            }) => match v.as_str() {
+            LexerToken::BasicToken(
+                token @ Token {
+                    kind: TokenKind::Number,
+                    ..
+                },
+            ) => Pattern::Literal(Literal::Number(parse_number(token)?)),
+            LexerToken::BasicToken(
+                token @ Token {
+                    kind: TokenKind::Identifier,
+                    value: v,
+                    ..
+                },
+            ) => match v.as_str() {
                 "N" => Pattern::Literal(Literal::Null),
                 "_" => Pattern::Any,
-                u => Pattern::Identifier(u.to_owned()),
+                u => Pattern::Identifier(u.to_owned(), PatternPosition::new(token)),
             },
             LexerToken::BasicToken(Token {
                 kind: TokenKind::String,
                 value,
                 ..
-            }) => Pattern::Literal(Literal::new_string_literal(value.clone().as_bytes())),
+            }) => Pattern::Literal(Literal::new_string_literal(value)),
             t => {
                 return Err(errors::CellTailError::new(
                     &input,
@@ -51,7 +56,7 @@ pub(super) fn parse_as_pattern(input: TokenGroup) -> errors::CellTailResult<Patt
             input
                 .split_all(TokenKind::Comma)
                 .into_iter()
-                .map(|i| parse_as_pattern(i))
+                .map(parse_as_pattern)
                 .collect::<errors::CellTailResult<_>>()?,
         ));
     }
@@ -61,7 +66,7 @@ pub(super) fn parse_as_pattern(input: TokenGroup) -> errors::CellTailResult<Patt
             input
                 .split_all(TokenKind::Operator('&'))
                 .into_iter()
-                .map(|i| parse_as_pattern(i))
+                .map(parse_as_pattern)
                 .collect::<errors::CellTailResult<Vec<Pattern>>>()?,
         ));
     }
@@ -71,8 +76,9 @@ pub(super) fn parse_as_pattern(input: TokenGroup) -> errors::CellTailResult<Patt
             input
                 .split_all(TokenKind::Operator('|'))
                 .into_iter()
-                .map(|i| parse_as_pattern(i))
+                .map(parse_as_pattern)
                 .collect::<errors::CellTailResult<Vec<Pattern>>>()?,
+            PatternPosition::new(&input),
         ));
     }
 
@@ -93,16 +99,16 @@ pub(super) fn parse_as_pattern(input: TokenGroup) -> errors::CellTailResult<Patt
         } else {
             Err(errors::CellTailError::new(
                 &input,
-                format!("Invalid range pattern"),
+                "Invalid range pattern".to_string(),
             ))?;
         }
     }
 
-    Ok(Pattern::Expression(parse_expression::parse_as_expression(
-        input,
-    ).map_err(
-        |err| err.map_description(|q|format!("Error parsing pattern as expression: {q}"))
-    )?))
+    Ok(Pattern::Expression(
+        parse_expression::parse_as_expression(input).map_err(|err| {
+            err.map_description(|q| format!("Error parsing pattern as expression: {q}"))
+        })?,
+    ))
 
     // Err(errors::CellTailError::new(
     //     &input,
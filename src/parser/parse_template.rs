@@ -0,0 +1,158 @@
+use crate::errors;
+use crate::lexer::{LexerToken, TokenGroup};
+use crate::parser::parse_expression::parse_as_constant;
+use crate::parser::parse_number::parse_as_number;
+use crate::runtime::expression::Expression;
+use crate::runtime::literal::Literal;
+use crate::runtime::pattern::Pattern;
+use crate::tokenizer::{Token, TokenKind};
+
+// Keeps a typo like (0..100000000) from hanging the parser
+pub(super) const MAX_TEMPLATE_RULES: usize = 10_000;
+
+pub(super) struct Template {
+    pub name: String,
+    pub values: Vec<Literal>,
+    pub body: TokenGroup,
+}
+
+fn parse_template_values(input: &TokenGroup) -> errors::CellTailResult<Vec<Literal>> {
+    match input.delimiter {
+        Some('[') => input
+            .split_all(TokenKind::Comma)
+            .into_iter()
+            .map(|value| parse_as_constant(value, "Template values"))
+            .collect(),
+        Some('(') => {
+            let (start, _, end) = input.split_first(TokenKind::Elipsis).ok_or_else(|| {
+                errors::CellTailError::new(
+                    input,
+                    "Expected a range like (0..10) or a list like [1, 2, 3]".to_owned(),
+                )
+            })?;
+            let start = parse_as_number(&start)?;
+            let end = parse_as_number(&end)?;
+
+            let first = start.checked_add(1).ok_or_else(|| {
+                errors::CellTailError::new(
+                    input,
+                    format!("Template range start {start} is too large"),
+                )
+            })?;
+            if end.saturating_sub(first) > MAX_TEMPLATE_RULES as isize {
+                Err(errors::CellTailError::new(
+                    input,
+                    format!("Template range has more than {MAX_TEMPLATE_RULES} values"),
+                ))?
+            }
+            Ok((first..end).map(Literal::Number).collect())
+        }
+        _ => Err(errors::CellTailError::new(
+            input,
+            "Expected a range like (0..10) or a list like [1, 2, 3]".to_owned(),
+        )),
+    }
+}
+
+pub(super) fn parse_template(input: TokenGroup) -> errors::CellTailResult<Template> {
+    match input.contents.as_slice() {
+        [LexerToken::BasicToken(Token {
+            kind: TokenKind::Identifier,
+            value: name,
+            ..
+        }), LexerToken::BasicToken(Token {
+            kind: TokenKind::Identifier,
+            value: keyword,
+            ..
+        }), LexerToken::Group(values), body @ ..]
+            if keyword == "in" =>
+        {
+            if name == "N" || name == "_" {
+                return Err(errors::CellTailError::new(
+                    &input.contents[0],
+                    format!("{name:?} can't be used as a template variable"),
+                ));
+            }
+
+            Ok(Template {
+                name: name.clone(),
+                values: parse_template_values(values)?,
+                body: TokenGroup {
+                    delimiter: input.delimiter,
+                    brackets: None,
+                    contents: body.to_vec(),
+                },
+            })
+        }
+        _ => Err(errors::CellTailError::new(
+            &input,
+            "Invalid template, expected \"for name in [values] rule\" or \"for name in (start..end) rule\"".to_owned(),
+        )),
+    }
+}
+
+pub(super) fn substitute_pattern(pattern: &Pattern, name: &str, value: &Literal) -> Pattern {
+    match pattern {
+        Pattern::Identifier(ident, _) if ident == name => Pattern::Literal(value.clone()),
+        Pattern::Literal(_) | Pattern::Identifier(..) | Pattern::Any => pattern.clone(),
+        Pattern::Tuple(parts) => Pattern::Tuple(
+            parts
+                .iter()
+                .map(|i| substitute_pattern(i, name, value))
+                .collect(),
+        ),
+        Pattern::And(parts) => Pattern::And(
+            parts
+                .iter()
+                .map(|i| substitute_pattern(i, name, value))
+                .collect(),
+        ),
+        Pattern::Or(parts, position) => Pattern::Or(
+            parts
+                .iter()
+                .map(|i| substitute_pattern(i, name, value))
+                .collect(),
+            position.clone(),
+        ),
+        Pattern::Expression(expression) => {
+            Pattern::Expression(substitute_expression(expression, name, value))
+        }
+        Pattern::Range(start, end) => Pattern::Range(
+            start
+                .as_ref()
+                .map(|i| Box::new(substitute_expression(i, name, value))),
+            end.as_ref()
+                .map(|i| Box::new(substitute_expression(i, name, value))),
+        ),
+    }
+}
+
+pub(super) fn substitute_expression(
+    expression: &Expression,
+    name: &str,
+    value: &Literal,
+) -> Expression {
+    match expression {
+        Expression::Variable(var, _) if var == name => Expression::Literal(value.clone()),
+        Expression::Literal(_) | Expression::Variable(..) => expression.clone(),
+        Expression::Tuple(parts) => Expression::Tuple(
+            parts
+                .iter()
+                .map(|i| substitute_expression(i, name, value))
+                .collect(),
+        ),
+        Expression::BinaryOperator(op, a, b) => Expression::BinaryOperator(
+            *op,
+            Box::new(substitute_expression(a, name, value)),
+            Box::new(substitute_expression(b, name, value)),
+        ),
+        Expression::UnaryOperator(op, a) => {
+            Expression::UnaryOperator(*op, Box::new(substitute_expression(a, name, value)))
+        }
+        Expression::FunctionCall(function_name, argument, position) => Expression::FunctionCall(
+            function_name.clone(),
+            Box::new(substitute_expression(argument, name, value)),
+            position.clone(),
+        ),
+    }
+}
@@ -1,22 +1,86 @@
-#[derive(Debug)]
+use crate::runtime::literal::Literal;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum IOFormat {
     Characters,
     Numbers,
+    // Whole values, written like in a program
+    Values,
+    Json,
+    // A list of characters for every line
+    Lines,
+    Bytes,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum InputSource {
     StdIn(IOFormat),
     Arg(IOFormat),
-    Constant(Vec<isize>),
+    Constant(Vec<Literal>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Attributes {
     pub input_mode: InputSource,
     pub output_mode: IOFormat,
     pub debug: bool,
-    pub max_iterations: Option<isize>
+    pub strict: bool,
+    pub max_iterations: Option<isize>,
+}
+
+// Every name of an output mode, the first one is the main name
+const OUTPUT_NAMES: [(IOFormat, &[&str]); 6] = [
+    (IOFormat::Characters, &["characters", "chars", "c"]),
+    (IOFormat::Numbers, &["numbers", "decimal", "n", "d"]),
+    (IOFormat::Values, &["values", "v"]),
+    (IOFormat::Json, &["json", "j"]),
+    (IOFormat::Lines, &["lines", "l"]),
+    (IOFormat::Bytes, &["bytes", "b"]),
+];
+
+impl IOFormat {
+    pub fn from_output_name(name: &str) -> Option<IOFormat> {
+        let name = name.to_lowercase();
+        OUTPUT_NAMES
+            .iter()
+            .find(|(_, names)| names.contains(&name.as_str()))
+            .map(|(format, _)| *format)
+    }
+
+    pub fn output_names() -> impl Iterator<Item = &'static str> {
+        OUTPUT_NAMES.iter().map(|(_, names)| names[0])
+    }
+
+    // The names worth suggesting for a misspelled output mode
+    pub fn output_spellings() -> impl Iterator<Item = &'static str> {
+        OUTPUT_NAMES
+            .iter()
+            .flat_map(|(_, names)| names.iter().copied())
+            .filter(|i| i.len() > 1)
+    }
+}
+
+// The values of a constant input as written in a program. A single string
+// would be read as one cell for every character, so it gets brackets
+pub fn constant_source(values: &[Literal], compact: bool) -> String {
+    let source = |i: &Literal| {
+        if compact {
+            i.compact_source()
+        } else {
+            i.source().to_string()
+        }
+    };
+    let separator = if compact { "," } else { ", " };
+    match values {
+        [] => "\"\"".to_owned(),
+        [value] if value.as_text().is_some() => format!("({})", source(value)),
+        _ => values
+            .iter()
+            .map(source)
+            .collect::<Vec<_>>()
+            .join(separator),
+    }
 }
 
 impl Attributes {
@@ -25,7 +89,38 @@ impl Attributes {
             input_mode: InputSource::Arg(IOFormat::Characters),
             output_mode: IOFormat::Characters,
             debug: false,
-            max_iterations: None
+            strict: false,
+            max_iterations: None,
+        }
+    }
+}
+
+impl fmt::Display for IOFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IOFormat::Characters => write!(f, "CHARS"),
+            IOFormat::Numbers => write!(f, "NUMBERS"),
+            IOFormat::Values => write!(f, "VALUES"),
+            IOFormat::Json => write!(f, "JSON"),
+            IOFormat::Lines => write!(f, "LINES"),
+            IOFormat::Bytes => write!(f, "BYTES"),
+        }
+    }
+}
+
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.input_mode {
+            InputSource::StdIn(format) => writeln!(f, "I=STDIN {format};")?,
+            InputSource::Arg(format) => writeln!(f, "I=CMD {format};")?,
+            InputSource::Constant(values) => writeln!(f, "I={};", constant_source(values, false))?,
+        }
+        writeln!(f, "O={};", self.output_mode)?;
+        writeln!(f, "D={};", self.debug)?;
+        writeln!(f, "S={};", self.strict)?;
+        if let Some(max) = self.max_iterations {
+            writeln!(f, "M={max};")?;
         }
+        Ok(())
     }
 }
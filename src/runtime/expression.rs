@@ -1,8 +1,10 @@
+use crate::errors;
 use crate::runtime::literal::Literal;
-use crate::runtime::pattern_list::PatternList;
+use crate::runtime::pattern_list::{PatternList, PatternPosition};
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinaryOperator {
     Add,
     Subtract,
@@ -34,39 +36,38 @@ impl BinaryOperator {
                 // This is provisional, probably want to do something actually useful with this combination of types
                 b @ Literal::Tuple(_) => Literal::Tuple(vec![Literal::Number(n), b]),
             },
-            Literal::Tuple(b) => Literal::Tuple(
-                [
-                    b[..b.len() - 1].to_vec(),
-                    vec![self.apply(b[b.len() - 1].clone(), op2)],
-                ]
-                .concat(),
-            ),
+            Literal::Tuple(b) => match b.split_last() {
+                Some((last, rest)) => {
+                    Literal::Tuple([rest.to_vec(), vec![self.apply(last.clone(), op2)]].concat())
+                }
+                None => Literal::Tuple(b),
+            },
         }
     }
 
     fn apply_add(op1: isize, op2: isize) -> isize {
-        op1 + op2
+        op1.wrapping_add(op2)
     }
 
     fn apply_sub(op1: isize, op2: isize) -> isize {
-        op1 - op2
+        op1.wrapping_sub(op2)
     }
 
     fn apply_mul(op1: isize, op2: isize) -> isize {
-        op1 * op2
+        op1.wrapping_mul(op2)
     }
     fn apply_div(op1: isize, op2: isize) -> Literal {
         if op2 == 0 {
             Literal::Null
         } else {
-            Literal::Number(op1 / op2)
+            Literal::Number(op1.wrapping_div(op2))
         }
     }
     fn apply_mod(op1: isize, op2: isize) -> Literal {
         if op2 == 0 {
             Literal::Null
         } else {
-            Literal::Number(op1 % op2)
+            Literal::Number(op1.wrapping_rem(op2))
         }
     }
 
@@ -80,9 +81,26 @@ impl BinaryOperator {
     fn apply_xor(op1: isize, op2: isize) -> isize {
         op1 ^ op2
     }
+
+    fn symbol(self) -> char {
+        match self {
+            BinaryOperator::Add => '+',
+            BinaryOperator::Subtract => '-',
+            BinaryOperator::Multiply => '*',
+            BinaryOperator::Divide => '/',
+            BinaryOperator::Xor => '^',
+            BinaryOperator::Mod => '%',
+        }
+    }
+
+    // The parser splits on the first of these operators it finds, so the
+    // earlier ones bind less strongly
+    fn precedence(self) -> usize {
+        self as usize
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOperator {
     Neg,
     Not,
@@ -92,15 +110,14 @@ impl UnaryOperator {
     fn apply(self, value: Literal) -> Literal {
         match self {
             UnaryOperator::Neg => match value {
-                Literal::Number(v) => Literal::Number(-v),
+                Literal::Number(v) => Literal::Number(v.wrapping_neg()),
                 Literal::Null => Literal::Null,
-                Literal::Tuple(k) => Literal::Tuple(
-                    [
-                        k[..k.len() - 1].to_vec(),
-                        vec![self.apply(k[k.len() - 1].clone())],
-                    ]
-                    .concat(),
-                ),
+                Literal::Tuple(k) => match k.split_last() {
+                    Some((last, rest)) => {
+                        Literal::Tuple([rest.to_vec(), vec![self.apply(last.clone())]].concat())
+                    }
+                    None => Literal::Tuple(k),
+                },
             },
             UnaryOperator::Not => match value {
                 Literal::Number(v) => Literal::Number(!v),
@@ -110,8 +127,16 @@ impl UnaryOperator {
         }
     }
 
-    fn array_reverse(_tuple: Vec<Literal>) -> Literal {
-        todo!()
+    fn array_reverse(mut tuple: Vec<Literal>) -> Literal {
+        tuple.reverse();
+        Literal::Tuple(tuple)
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            UnaryOperator::Neg => '-',
+            UnaryOperator::Not => '!',
+        }
     }
 }
 
@@ -121,22 +146,39 @@ pub enum Expression {
     Tuple(Vec<Expression>),
     BinaryOperator(BinaryOperator, Box<Expression>, Box<Expression>),
     UnaryOperator(UnaryOperator, Box<Expression>),
-    FunctionCall(String, Box<Expression>),
-    Variable(String),
+    FunctionCall(String, Box<Expression>, PatternPosition),
+    Variable(String, PatternPosition),
 }
 
 impl Expression {
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Expression::Literal(_) => true,
+            Expression::Tuple(parts) => parts.iter().all(Expression::is_constant),
+            Expression::BinaryOperator(_, a, b) => a.is_constant() && b.is_constant(),
+            Expression::UnaryOperator(_, a) => a.is_constant(),
+            Expression::Variable(name, _) => name == "N",
+            Expression::FunctionCall(..) => false,
+        }
+    }
+
+    // In strict mode calling a function with an argument none of its
+    // patterns match is an error, otherwise it results in null and a warning
     pub fn evaluate(
         &self,
         vars: &HashMap<String, Literal>,
         functions: &HashMap<String, PatternList>,
-    ) -> Literal {
-        match self {
+        strict: bool,
+        warnings: &mut Vec<errors::CellTailError>,
+    ) -> errors::CellTailResult<Literal> {
+        Ok(match self {
             Expression::Literal(v) => v.clone(),
-            Expression::Tuple(v) => {
-                Literal::Tuple(v.iter().map(|i| i.evaluate(vars, functions)).collect())
-            }
-            Expression::Variable(name) => {
+            Expression::Tuple(v) => Literal::Tuple(
+                v.iter()
+                    .map(|i| i.evaluate(vars, functions, strict, warnings))
+                    .collect::<errors::CellTailResult<_>>()?,
+            ),
+            Expression::Variable(name, _) => {
                 if name == "N" {
                     Literal::Null
                 } else {
@@ -144,31 +186,119 @@ impl Expression {
                 }
             }
             Expression::BinaryOperator(op, ex1, ex2) => op.apply(
-                Self::evaluate(&**ex1, vars, functions),
-                Self::evaluate(&**ex2, vars, functions),
+                Self::evaluate(ex1, vars, functions, strict, warnings)?,
+                Self::evaluate(ex2, vars, functions, strict, warnings)?,
             ),
-            Expression::FunctionCall(function_name, argument) => {
-                let function_argument = argument.evaluate(vars, functions);
-
-                if let Some(value) = functions
-                    .get(function_name)
-                    .expect(&format!(
-                        "Can't find a function with name {}",
-                        function_name
-                    ))
-                    .apply_first_matching_pattern(function_argument.clone(), functions)
-                {
+            Expression::FunctionCall(function_name, argument, position) => {
+                let function_argument = argument.evaluate(vars, functions, strict, warnings)?;
+
+                let function = functions.get(function_name).ok_or_else(|| {
+                    errors::CellTailError::new(
+                        position,
+                        format!("Can't find a function with name {function_name:?}"),
+                    )
+                })?;
+                if let Some(value) = function.apply_first_matching_pattern(
+                    function_argument.clone(),
+                    functions,
+                    strict,
+                    warnings,
+                )? {
                     value
+                } else if strict {
+                    Err(errors::CellTailError::new(
+                        position,
+                        format!(
+                            "None of the patterns of function {function_name:?} match the argument {function_argument}"
+                        ),
+                    ))?
                 } else {
-                    eprintln!(
-                        "WARNING! Attempt to call function {} with invalid arguments {:?}",
-                        function_name, argument
+                    // Only warn once for every call
+                    let warning = errors::CellTailError::warning(
+                        position,
+                        errors::WarningCode::NonExhaustive,
+                        format!("None of the patterns of function {function_name:?} match the argument, the call results in N"),
                     );
-                    return Literal::Null;
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                    Literal::Null
                 }
             }
             Expression::UnaryOperator(operator, value) => {
-                operator.apply(value.evaluate(vars, functions))
+                operator.apply(value.evaluate(vars, functions, strict, warnings)?)
+            }
+        })
+    }
+}
+
+impl Expression {
+    fn starts_with_operator(&self) -> bool {
+        match self {
+            Expression::UnaryOperator(..) => true,
+            Expression::Literal(Literal::Number(n)) => *n < 0,
+            _ => false,
+        }
+    }
+
+    // The precedence of the loosest operator outside of brackets when the
+    // expression is written out
+    fn loosest_operator(&self) -> Option<usize> {
+        match self {
+            Expression::BinaryOperator(op, ..) => Some(op.precedence()),
+            Expression::UnaryOperator(UnaryOperator::Neg, _) => {
+                Some(BinaryOperator::Subtract.precedence())
+            }
+            _ if self.starts_with_operator() => Some(BinaryOperator::Subtract.precedence()),
+            _ => None,
+        }
+    }
+
+    fn write_bracketed(&self, f: &mut fmt::Formatter, bracketed: bool) -> fmt::Result {
+        if bracketed {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Literal(literal) => literal.write_source(f),
+            Expression::Variable(name, _) => write!(f, "{name}"),
+            Expression::Tuple(parts) => {
+                write!(f, "(")?;
+                for (index, part) in parts.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{part}")?;
+                }
+                write!(f, ")")
+            }
+            Expression::FunctionCall(name, argument, _) => {
+                write!(f, "{name}")?;
+                argument.write_bracketed(f, !matches!(**argument, Expression::Tuple(_)))
+            }
+            Expression::UnaryOperator(op, value) => {
+                write!(f, "{}", op.symbol())?;
+                value.write_bracketed(
+                    f,
+                    value.loosest_operator().is_some() || value.starts_with_operator(),
+                )
+            }
+            Expression::BinaryOperator(op, a, b) => {
+                // An operator at the start would make the whole expression
+                // unary, and a is split off at the first operator found
+                a.write_bracketed(
+                    f,
+                    a.starts_with_operator()
+                        || a.loosest_operator().is_some_and(|i| i <= op.precedence()),
+                )?;
+                write!(f, " {} ", op.symbol())?;
+                b.write_bracketed(f, b.loosest_operator().is_some_and(|i| i < op.precedence()))
             }
         }
     }
@@ -8,38 +8,172 @@ pub enum Literal {
 }
 
 impl Literal {
-    pub fn new_string_literal(item: &[u8]) -> Literal {
-        if item.len() == 0 {
-            return Literal::Null;
-        } else {
-            return Literal::Tuple(vec![
-                Literal::Number(item[0] as isize),
-                Literal::new_string_literal(&item[1..]),
-            ]);
+    pub fn new_string_literal(item: &str) -> Literal {
+        let mut characters = item.chars();
+        match characters.next() {
+            None => Literal::Null,
+            Some(first) => Literal::Tuple(vec![
+                Literal::Number(first as u32 as isize),
+                Literal::new_string_literal(characters.as_str()),
+            ]),
         }
     }
-}
 
-impl fmt::Display for Literal {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Write strictly the first element into the supplied output
-        // stream: `f`. Returns `fmt::Result` which indicates whether the
-        // operation succeeded or failed. Note that `write!` uses syntax which
-        // is very similar to `println!`.
+    // The elements of a list built from pairs like (1, (2, (3, N)))
+    pub fn as_list(&self) -> Option<Vec<&Literal>> {
+        let mut elements = vec![];
+        let mut rest = self;
+        while let Literal::Tuple(pair) = rest {
+            let [head, tail] = pair.as_slice() else {
+                return None;
+            };
+            elements.push(head);
+            rest = tail;
+        }
+        (rest == &Literal::Null && !elements.is_empty()).then_some(elements)
+    }
+
+    // Lists of code points of readable text, these are written as strings
+    pub fn as_text(&self) -> Option<String> {
+        self.as_list()?
+            .into_iter()
+            .map(|i| match i {
+                Literal::Number(n) => char::from_u32((*n).try_into().ok()?)
+                    .filter(|c| !c.is_control() || matches!(c, '\n' | '\t' | '\r')),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Tuples with one value, like the JSON input [[5]], have no spelling in a
+    // program since (5) is just the number 5
+    pub fn contains_single_tuple(&self) -> bool {
         match self {
-            Literal::Number(k) => {
-                f.pad(&format!("{}", k))
-            }
-            Literal::Null => {
-                write!(f, "NULL")
+            Literal::Tuple(values) => {
+                values.len() == 1 || values.iter().any(Literal::contains_single_tuple)
             }
-            Literal::Tuple(tup) => {
-                write!(f, "(")?;
-                for elem in tup {
-                    write!(f, "{}, ", elem)?;
+            _ => false,
+        }
+    }
+
+    fn write(&self, out: &mut impl fmt::Write, style: Style, separator: &str) -> fmt::Result {
+        let Literal::Tuple(values) = self else {
+            return match self {
+                Literal::Number(isize::MIN) if style == Style::Source => {
+                    let space = separator.trim_start_matches(',');
+                    write!(out, "(-{}{space}-{space}1)", isize::MAX)
                 }
-                write!(f, ")")
+                Literal::Number(n) => write!(out, "{n}"),
+                _ => write!(out, "N"),
+            };
+        };
+
+        if style >= Style::Text {
+            let text = self.as_text().and_then(|text| {
+                string_literal(&text.chars().map(|i| i as u32 as isize).collect::<Vec<_>>())
+            });
+            if let Some(text) = text {
+                return write!(out, "{text}");
+            }
+        }
+        let (open, close, values) = match self.as_list() {
+            Some(list) if style >= Style::Lists => ('[', ']', list),
+            _ => ('(', ')', values.iter().collect()),
+        };
+        write!(out, "{open}")?;
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                write!(out, "{separator}")?;
+            }
+            value.write(out, style, separator)?;
+        }
+        write!(out, "{close}")
+    }
+
+    // Writes the literal the way it would be written in a program
+    pub fn write_source(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, Style::Source, ", ")
+    }
+
+    // Displays the literal the way it would be written in a program
+    pub fn source(&self) -> LiteralDisplay<'_> {
+        LiteralDisplay(self, Style::Source)
+    }
+
+    // Like source, but without spaces between the values, used by golf
+    pub fn compact_source(&self) -> String {
+        let mut text = String::new();
+        self.write(&mut text, Style::Source, ",")
+            .expect("Writing to a string can't fail");
+        text
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Literal::Null => serde_json::Value::Null,
+            Literal::Number(n) => (*n).into(),
+            Literal::Tuple(values) => values.iter().map(Literal::to_json).collect(),
+        }
+    }
+
+    // Lists of characters are only displayed as strings if the values are
+    // meant to be text, otherwise [104, 105] would be shown as "hi"
+    pub fn display(&self, text: bool) -> LiteralDisplay<'_> {
+        LiteralDisplay(self, if text { Style::Text } else { Style::Lists })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Style {
+    // Only tuples, like (1, (2, N))
+    Raw,
+    Lists,
+    Text,
+    Source,
+}
+
+// The alternate flag, like {:#}, shows the tuples lists are made of
+fn pad(f: &mut fmt::Formatter, literal: &Literal, style: Style) -> fmt::Result {
+    let mut text = String::new();
+    literal.write(
+        &mut text,
+        if f.alternate() { Style::Raw } else { style },
+        ", ",
+    )?;
+    f.pad(&text)
+}
+
+pub struct LiteralDisplay<'a>(&'a Literal, Style);
+
+impl fmt::Display for LiteralDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        pad(f, self.0, self.1)
+    }
+}
+
+// Writes code points as a string literal, None if one isn't a character
+pub fn string_literal(values: &[isize]) -> Option<String> {
+    let mut result = String::from('"');
+    for value in values {
+        match char::from_u32((*value).try_into().ok()?)? {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            character if character.is_control() => {
+                result.push_str(&format!("\\u{{{:x}}}", character as u32))
             }
+            character => result.push(character),
         }
     }
+    result.push('"');
+    Some(result)
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        pad(f, self, Style::Lists)
+    }
 }
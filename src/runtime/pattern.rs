@@ -1,30 +1,31 @@
+use crate::errors;
 use crate::runtime::expression::Expression;
 use crate::runtime::literal::Literal;
+use crate::runtime::pattern_list::PatternPosition;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone)]
 pub enum Pattern {
     Literal(Literal),
-    Identifier(String),
+    Identifier(String, PatternPosition),
     Any,
     Tuple(Vec<Pattern>),
     Expression(Expression),
     And(Vec<Pattern>),
-    Or(Vec<Pattern>),
+    Or(Vec<Pattern>, PatternPosition),
     Range(Option<Box<Expression>>, Option<Box<Expression>>),
 }
 
 impl Pattern {
-    pub fn match_dict(&self, value: &Literal, variables: &mut HashMap<String, Literal>) -> bool {
-        match self {
-            Pattern::Literal(lit) => {
-                if lit == value {
-                    true
-                } else {
-                    false
-                }
-            }
-            Pattern::Identifier(ident) => {
+    pub fn match_dict(
+        &self,
+        value: &Literal,
+        variables: &mut HashMap<String, Literal>,
+    ) -> errors::CellTailResult<bool> {
+        Ok(match self {
+            Pattern::Literal(lit) => lit == value,
+            Pattern::Identifier(ident, _) => {
                 if variables.contains_key(ident) {
                     variables.get(ident) == Some(value)
                 } else {
@@ -37,55 +38,116 @@ impl Pattern {
                     if tup2.len() != tup1.len() {
                         false
                     } else {
-                        tup1.iter()
-                            .zip(tup2)
-                            .all(|(pat, val)| pat.match_dict(val, variables))
+                        for (pat, val) in tup1.iter().zip(tup2) {
+                            if !pat.match_dict(val, variables)? {
+                                return Ok(false);
+                            }
+                        }
+                        true
                     }
                 } else {
                     false
                 }
             }
             Pattern::Expression(expr) => {
-                let new_value = expr.evaluate(variables, &HashMap::new());
+                let new_value = expr.evaluate(variables, &HashMap::new(), true, &mut vec![])?;
                 &new_value == value
             }
-            Pattern::And(parts) => return parts.iter().all(|i| i.match_dict(value, variables)),
-            Pattern::Or(parts) => {
+            Pattern::And(parts) => {
+                for part in parts {
+                    if !part.match_dict(value, variables)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Pattern::Or(parts, _) => {
                 for part in parts {
                     let mut copy = variables.clone();
-                    if part.match_dict(value, &mut copy) {
+                    if part.match_dict(value, &mut copy)? {
                         *variables = copy;
-                        return true;
+                        return Ok(true);
                     }
                 }
-                return false;
+                false
             }
             Pattern::Range(ba, be) => {
                 let first_part = if let Some(expr) = ba {
-                    &expr.evaluate(variables, &HashMap::new()) < value
+                    &expr.evaluate(variables, &HashMap::new(), true, &mut vec![])? < value
                 } else {
                     true
                 };
 
                 let second_part = if let Some(expr) = be {
-                    value < &expr.evaluate(variables, &HashMap::new())
+                    value < &expr.evaluate(variables, &HashMap::new(), true, &mut vec![])?
                 } else {
                     true
                 };
 
-                return first_part && second_part;
+                first_part && second_part
             }
             Pattern::Any => true,
-        }
+        })
     }
 
-    pub fn matches(&self, value: &Literal) -> Option<HashMap<String, Literal>> {
+    pub fn matches(
+        &self,
+        value: &Literal,
+    ) -> errors::CellTailResult<Option<HashMap<String, Literal>>> {
         let mut result = HashMap::new();
 
-        if self.match_dict(value, &mut result) {
-            Some(result)
+        if self.match_dict(value, &mut result)? {
+            Ok(Some(result))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// Nested & and | would otherwise be flattened into this one
+fn write_parts(f: &mut fmt::Formatter, parts: &[Pattern], separator: &str) -> fmt::Result {
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            write!(f, "{separator}")?;
+        }
+        if matches!(part, Pattern::And(_) | Pattern::Or(..)) {
+            write!(f, "({part})")?;
         } else {
-            None
+            write!(f, "{part}")?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Literal(literal) => literal.write_source(f),
+            Pattern::Identifier(name, _) => write!(f, "{name}"),
+            Pattern::Any => write!(f, "_"),
+            Pattern::Tuple(parts) => {
+                write!(f, "(")?;
+                for (index, part) in parts.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{part}")?;
+                }
+                write!(f, ")")
+            }
+            Pattern::Expression(expression) => write!(f, "{expression}"),
+            Pattern::And(parts) => write_parts(f, parts, " & "),
+            Pattern::Or(parts, _) => write_parts(f, parts, " | "),
+            Pattern::Range(start, end) => {
+                if let Some(start) = start {
+                    write!(f, "{start}")?;
+                }
+                write!(f, "..")?;
+                if let Some(end) = end {
+                    write!(f, "{end}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
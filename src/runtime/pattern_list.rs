@@ -3,6 +3,7 @@ use crate::runtime::expression::Expression;
 use crate::runtime::literal::Literal;
 use crate::runtime::pattern::Pattern;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug)]
 pub struct PatternList(pub Vec<(Pattern, Expression, PatternPosition)>);
@@ -12,19 +13,59 @@ impl PatternList {
         &self,
         literal: Literal,
         functions: &HashMap<String, PatternList>,
-    ) -> Option<Literal> {
-        for (pattern, expression, _error_range) in &self.0 {
-            if let Some(caputred_variables) = (pattern).matches(&literal) {
-                return Some(expression.evaluate(&caputred_variables, functions));
+        strict: bool,
+        warnings: &mut Vec<errors::CellTailError>,
+    ) -> errors::CellTailResult<Option<Literal>> {
+        for (pattern, expression, position) in &self.0 {
+            let in_rule = |error: errors::CellTailError| error.in_rule(position);
+            if let Some(caputred_variables) = (pattern).matches(&literal).map_err(in_rule)? {
+                return Ok(Some(
+                    expression
+                        .evaluate(&caputred_variables, functions, strict, warnings)
+                        .map_err(in_rule)?,
+                ));
             }
         }
 
-        None
+        Ok(None)
     }
 }
 
-#[derive(Debug)]
+// Tuples at the top of a rule don't need brackets
+fn without_brackets<T: fmt::Display>(parts: &[T]) -> String {
+    parts
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl PatternList {
+    pub fn write_rules(&self, f: &mut fmt::Formatter, prefix: &str) -> fmt::Result {
+        for (pattern, expression, _) in &self.0 {
+            let pattern = match pattern {
+                Pattern::Tuple(parts) if parts.len() > 1 => without_brackets(parts),
+                _ => pattern.to_string(),
+            };
+            let expression = match expression {
+                Expression::Tuple(parts) if parts.len() > 1 => without_brackets(parts),
+                _ => expression.to_string(),
+            };
+            writeln!(f, "{prefix}{pattern}: {expression};")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PatternList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_rules(f, "")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PatternPosition {
+    file: Option<errors::FileId>,
     start: Option<usize>,
     end: Option<usize>,
 }
@@ -32,10 +73,15 @@ pub struct PatternPosition {
 impl PatternPosition {
     pub fn new<T: errors::SourceCodePosition>(m: &T) -> PatternPosition {
         PatternPosition {
+            file: m.get_file(),
             start: m.get_start(),
             end: m.get_end(),
         }
     }
+
+    pub fn set_file(&mut self, file: errors::FileId) {
+        self.file = Some(file);
+    }
 }
 
 impl errors::SourceCodePosition for PatternPosition {
@@ -45,4 +91,7 @@ impl errors::SourceCodePosition for PatternPosition {
     fn get_end(&self) -> Option<usize> {
         self.end
     }
+    fn get_file(&self) -> Option<errors::FileId> {
+        self.file
+    }
 }
@@ -1,4 +1,4 @@
-#[cfg(not(arch = "wasm32"))]
+#[cfg(not(target_arch = "wasm32"))]
 use atty::Stream;
 
 pub fn set_color<T: std::io::Write>(color: u8, destination: &mut T) {
@@ -7,12 +7,12 @@ pub fn set_color<T: std::io::Write>(color: u8, destination: &mut T) {
     }
 }
 
-#[cfg(not(arch = "wasm32"))]
+#[cfg(not(target_arch = "wasm32"))]
 fn is_tty() -> bool {
     atty::is(Stream::Stderr)
 }
 
-#[cfg(arch = "wasm32")]
+#[cfg(target_arch = "wasm32")]
 fn is_tty() -> bool {
     false
 }
\ No newline at end of file
@@ -33,7 +33,7 @@ impl errors::SourceCodePosition for Token {
     }
 }
 
-fn match_rest<T>(input: &Vec<char>, counter: &mut usize, match_fn: T, kind: TokenKind) -> Token
+fn match_rest<T>(input: &[char], counter: &mut usize, match_fn: T, kind: TokenKind) -> Token
 where
     T: Fn(char) -> bool,
 {
@@ -42,153 +42,442 @@ where
         *counter += 1;
     }
 
-    //*counter += 1;
-
     Token {
         kind,
-        start: start,
-        end: *counter + 1,
+        start,
+        end: *counter,
         value: input[start..*counter].iter().collect(),
     }
 }
 
-pub fn tokenize(input: &Vec<char>) -> errors::CellTailResult<Vec<Token>> {
-    let mut counter = 0;
-    let mut result: Vec<Token> = vec![];
+pub fn split_number_prefix(value: &str) -> (u32, &str) {
+    match value.get(..2) {
+        Some("0x" | "0X") => (16, &value[2..]),
+        Some("0o" | "0O") => (8, &value[2..]),
+        Some("0b" | "0B") => (2, &value[2..]),
+        _ => (10, value),
+    }
+}
 
-    while counter < input.len() {
-        match input[counter] {
-            ' ' | '\n' | '\t' | '\r' => counter += 1,
-            '#' => result.push(match_rest(
-                &input,
-                &mut counter,
-                |v: char| v != '\n',
-                TokenKind::Comment,
-            )),
-            '0'..='9' => result.push(match_rest(
-                &input,
-                &mut counter,
-                |v: char| v.is_numeric(),
-                TokenKind::Number,
-            )),
-            '\'' => {
-                if input[counter + 2] != '\'' {
-                    Err(errors::CellTailError::new(
-                        &errors::RangeError(counter, counter + 3),
-                        format!(
-                            "Character literal must end in a single quote ', got {:?}",
-                            input[counter + 2]
-                        ),
-                    ))?
-                }
-                result.push(Token {
-                    kind: TokenKind::Number,
-                    value: format!("{}", input[counter + 1] as u8),
-                    start: counter,
-                    end: counter + 3,
-                });
-
-                counter += 3
-            }
-            '"' => {
-                counter += 1;
-                result.push(match_rest(
-                    &input,
-                    &mut counter,
-                    |v: char| v != '"',
-                    TokenKind::String,
-                ));
-                counter += 1;
-            }
-            'a'..='z' | 'A'..='Z' | '_' => result.push(match_rest(
-                &input,
-                &mut counter,
-                |c: char| c.is_alphanumeric() || c == '_',
-                TokenKind::Identifier,
-            )),
-            '(' | '[' | '{' => {
-                result.push(Token {
-                    kind: TokenKind::OpeningBracket(input[counter]),
-                    start: counter,
-                    end: counter + 1,
-                    value: [input[counter]].iter().collect(),
-                });
-                counter += 1
-            }
-            ')' | ']' | '}' => {
-                result.push(Token {
-                    kind: TokenKind::ClosingBracket(input[counter]),
-                    start: counter,
-                    end: counter + 1,
-                    value: [input[counter]].iter().collect(),
-                });
-                counter += 1
-            }
-            x @ ('+' | '-' | '/' | '*' | '&' | '|' | '^' | '%') => result.push(match_rest(
-                &input,
-                &mut counter,
-                |c: char| c == x || c == '@',
-                TokenKind::Operator(x),
-            )),
-            ':' => {
-                result.push(Token {
-                    kind: TokenKind::Colon,
-                    start: counter,
-                    end: counter + 1,
-                    value: [input[counter]].iter().collect(),
-                });
-                counter += 1
+fn check_number_literal(token: &Token) -> errors::CellTailResult<()> {
+    let (radix, digits) = split_number_prefix(&token.value);
+    let digits_start = token.start + token.value.len() - digits.len();
+
+    if !digits.chars().any(|c| c != '_') {
+        Err(errors::CellTailError::new(
+            &errors::RangeError(token.start, digits_start + digits.len()),
+            format!(
+                "Expected digits after the prefix of number literal {:?}",
+                token.value
+            ),
+        ))?
+    }
+
+    if let Some((index, digit)) = digits
+        .chars()
+        .enumerate()
+        .find(|(_, c)| *c != '_' && !c.is_digit(radix))
+    {
+        Err(errors::CellTailError::new(
+            &errors::PointError(digits_start + index),
+            format!("Invalid digit {digit:?} in base {radix} number literal"),
+        ))?
+    }
+
+    Ok(())
+}
+
+fn read_character(
+    input: &[char],
+    counter: &mut usize,
+    quote: char,
+) -> errors::CellTailResult<char> {
+    let start = *counter;
+    let character = match input.get(*counter) {
+        None => Err(errors::CellTailError::new(
+            &errors::PointError(start.min(input.len().saturating_sub(1))),
+            format!("Unterminated literal, expected a closing {quote}"),
+        ))?,
+        Some('\\') => {
+            *counter += 1;
+            match input.get(*counter) {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                Some('\'') => '\'',
+                Some('u') => return read_unicode_escape(input, counter, start),
+                other => Err(errors::CellTailError::new(
+                    &errors::RangeError(start, (*counter + 1).min(input.len())),
+                    format!(
+                        "Unknown escape sequence \\{}",
+                        other.map(|i| i.to_string()).unwrap_or_default()
+                    ),
+                )
+                .with_note(
+                    "Valid escapes are \\n, \\t, \\r, \\0, \\\\, \\\", \\' and \\u{...}".to_owned(),
+                ))?,
             }
-            ',' => {
-                result.push(Token {
-                    kind: TokenKind::Comma,
-                    start: counter,
-                    end: counter + 1,
-                    value: [input[counter]].iter().collect(),
-                });
-                counter += 1
+        }
+        Some(k) => *k,
+    };
+    *counter += 1;
+    Ok(character)
+}
+
+// Reads the {...} part of a \u{...} escape, counter points at the u
+fn read_unicode_escape(
+    input: &[char],
+    counter: &mut usize,
+    start: usize,
+) -> errors::CellTailResult<char> {
+    *counter += 1;
+    if input.get(*counter) != Some(&'{') {
+        Err(errors::CellTailError::new(
+            &errors::RangeError(start, (*counter).min(input.len())),
+            "Expected a { after \\u".to_owned(),
+        ))?
+    }
+    *counter += 1;
+    let digits_start = *counter;
+    while *counter < input.len() && input[*counter].is_ascii_hexdigit() {
+        *counter += 1;
+    }
+    let digits: String = input[digits_start..*counter].iter().collect();
+    if input.get(*counter) != Some(&'}') {
+        Err(errors::CellTailError::new(
+            &errors::RangeError(start, (*counter).min(input.len())),
+            "Expected hexadecimal digits followed by a } in unicode escape".to_owned(),
+        ))?
+    }
+    *counter += 1;
+
+    u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| {
+            errors::CellTailError::new(
+                &errors::RangeError(start, *counter),
+                format!("\\u{{{digits}}} is not a valid unicode code point"),
+            )
+        })
+}
+
+fn tokenize_next(
+    input: &[char],
+    counter: &mut usize,
+    result: &mut Vec<Token>,
+) -> errors::CellTailResult<()> {
+    match input[*counter] {
+        ' ' | '\n' | '\t' | '\r' => *counter += 1,
+        '#' => result.push(match_rest(
+            input,
+            counter,
+            |v: char| v != '\n',
+            TokenKind::Comment,
+        )),
+        '0'..='9' => {
+            let token = match_rest(
+                input,
+                counter,
+                |v: char| v.is_ascii_alphanumeric() || v == '_',
+                TokenKind::Number,
+            );
+            check_number_literal(&token)?;
+            result.push(token)
+        }
+        '\'' => {
+            let start = *counter;
+            *counter += 1;
+            let character = read_character(input, counter, '\'')?;
+            if input.get(*counter) != Some(&'\'') {
+                Err(errors::CellTailError::new(
+                    &errors::RangeError(start, *counter),
+                    format!(
+                        "Character literal must end in a single quote ', got {:?}",
+                        input.get(*counter)
+                    ),
+                ))?
             }
-            ';' => {
-                result.push(Token {
-                    kind: TokenKind::Semicolon,
-                    start: counter,
-                    end: counter + 1,
-                    value: [input[counter]].iter().collect(),
-                });
-                counter += 1
+            *counter += 1;
+
+            result.push(Token {
+                kind: TokenKind::Number,
+                value: format!("{}", character as u32),
+                start,
+                end: *counter,
+            });
+        }
+        '"' => {
+            let start = *counter;
+            *counter += 1;
+            let mut value = String::new();
+            while input.get(*counter) != Some(&'"') {
+                value.push(read_character(input, counter, '"')?);
             }
-            '=' => {
-                result.push(Token {
-                    kind: TokenKind::Equals,
-                    start: counter,
-                    end: counter + 1,
-                    value: [input[counter]].iter().collect(),
-                });
-                counter += 1
+            *counter += 1;
+
+            result.push(Token {
+                kind: TokenKind::String,
+                start,
+                end: *counter,
+                value,
+            });
+        }
+        'a'..='z' | 'A'..='Z' | '_' => result.push(match_rest(
+            input,
+            counter,
+            |c: char| c.is_alphanumeric() || c == '_',
+            TokenKind::Identifier,
+        )),
+        '(' | '[' | '{' => {
+            result.push(Token {
+                kind: TokenKind::OpeningBracket(input[*counter]),
+                start: *counter,
+                end: *counter + 1,
+                value: [input[*counter]].iter().collect(),
+            });
+            *counter += 1
+        }
+        ')' | ']' | '}' => {
+            result.push(Token {
+                kind: TokenKind::ClosingBracket(input[*counter]),
+                start: *counter,
+                end: *counter + 1,
+                value: [input[*counter]].iter().collect(),
+            });
+            *counter += 1
+        }
+        x @ ('+' | '-' | '/' | '*' | '&' | '|' | '^' | '%') => result.push(match_rest(
+            input,
+            counter,
+            |c: char| c == x || c == '@',
+            TokenKind::Operator(x),
+        )),
+        ':' => {
+            result.push(Token {
+                kind: TokenKind::Colon,
+                start: *counter,
+                end: *counter + 1,
+                value: [input[*counter]].iter().collect(),
+            });
+            *counter += 1
+        }
+        ',' => {
+            result.push(Token {
+                kind: TokenKind::Comma,
+                start: *counter,
+                end: *counter + 1,
+                value: [input[*counter]].iter().collect(),
+            });
+            *counter += 1
+        }
+        ';' => {
+            result.push(Token {
+                kind: TokenKind::Semicolon,
+                start: *counter,
+                end: *counter + 1,
+                value: [input[*counter]].iter().collect(),
+            });
+            *counter += 1
+        }
+        '=' => {
+            result.push(Token {
+                kind: TokenKind::Equals,
+                start: *counter,
+                end: *counter + 1,
+                value: [input[*counter]].iter().collect(),
+            });
+            *counter += 1
+        }
+        '.' => {
+            if input.get(*counter + 1) != Some(&'.') {
+                Err(errors::CellTailError::new(
+                    &errors::PointError(*counter),
+                    "Range expected 2 .., found 1".to_string(),
+                ))?
             }
-            '.' => {
-                if input[counter + 1] != '.' {
-                    Err(errors::CellTailError::new(
-                        &errors::PointError(counter),
-                        format!("Range expected 2 .., found 1"),
-                    ))?
+            result.push(Token {
+                kind: TokenKind::Elipsis,
+                start: *counter,
+                end: *counter + 2,
+                value: "..".to_owned(),
+            });
+            *counter += 2
+        }
+        token => {
+            return Err(errors::CellTailError::new(
+                &errors::PointError(*counter),
+                format!("Unexpected token: {}", token),
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+// Moves the counter past the next ; that isn't part of a literal or comment
+fn skip_statement(input: &[char], counter: &mut usize) {
+    let mut quote = None;
+    while let Some(&character) = input.get(*counter) {
+        *counter += 1;
+        match (quote, character) {
+            (Some(_), '\\') => *counter += 1,
+            (Some(end), _) if character == end => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(character),
+            (None, '#') => {
+                while input.get(*counter).is_some_and(|c| *c != '\n') {
+                    *counter += 1;
                 }
-                result.push(Token {
-                    kind: TokenKind::Elipsis,
-                    start: counter,
-                    end: counter + 2,
-                    value: "..".to_owned(),
-                });
-                counter += 2
-            }
-            token => {
-                return Err(errors::CellTailError::new(
-                    &errors::PointError(counter),
-                    format!("Unexpected token: {}", token),
-                ))
             }
+            (None, ';') => return,
+            (None, _) => (),
+        }
+    }
+}
+
+pub fn tokenize(input: &[char], diagnostics: &mut Vec<errors::CellTailError>) -> Vec<Token> {
+    let mut counter = 0;
+    let mut result: Vec<Token> = vec![];
+
+    while counter < input.len() {
+        let token_start = counter;
+        if let Err(error) = tokenize_next(input, &mut counter, &mut result) {
+            diagnostics.push(error);
+
+            // Drop the rest of the statement, the statements after it can still be checked
+            let statement_start = result
+                .iter()
+                .rposition(|i| i.kind == TokenKind::Semicolon)
+                .map_or(0, |i| i + 1);
+            result.truncate(statement_start);
+            counter = token_start;
+            skip_statement(input, &mut counter);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SourceCodePosition;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        let mut diagnostics = vec![];
+        let result = tokenize(&source.chars().collect::<Vec<_>>(), &mut diagnostics);
+        assert!(diagnostics.is_empty(), "{source:?}: {diagnostics:?}");
+        result
+    }
+
+    fn single(source: &str) -> Token {
+        let result = tokens(source);
+        assert_eq!(result.len(), 1, "{source:?}: {result:?}");
+        result.into_iter().next().unwrap()
+    }
+
+    fn error(source: &str) -> errors::CellTailError {
+        let mut diagnostics = vec![];
+        tokenize(&source.chars().collect::<Vec<_>>(), &mut diagnostics);
+        assert_eq!(diagnostics.len(), 1, "{source:?}: {diagnostics:?}");
+        diagnostics.pop().unwrap()
+    }
+
+    #[test]
+    fn number_prefixes() {
+        for source in ["0x1F", "0XaB", "0o17", "0O7", "0b101", "0B1", "42"] {
+            let token = single(source);
+            assert_eq!(token.kind, TokenKind::Number);
+            assert_eq!(token.value, source);
+        }
+        assert_eq!(split_number_prefix("0x1F"), (16, "1F"));
+        assert_eq!(split_number_prefix("0o17"), (8, "17"));
+        assert_eq!(split_number_prefix("0b101"), (2, "101"));
+        assert_eq!(split_number_prefix("0101"), (10, "0101"));
+    }
+
+    #[test]
+    fn number_separators() {
+        for source in ["1_000_000", "0xFF_FF", "0b1010_0101", "0o_7", "1__2_"] {
+            assert_eq!(single(source).value, source);
         }
+        let result = tokens("x = 1_0;");
+        assert_eq!((result[2].start, result[2].end), (4, 7));
+    }
+
+    #[test]
+    fn invalid_digits() {
+        let binary = error("0b12");
+        assert_eq!((binary.get_start(), binary.get_end()), (Some(3), None));
+
+        let octal = error("x = 0o78;");
+        assert_eq!((octal.get_start(), octal.get_end()), (Some(7), None));
+
+        let hex = error("0x1_g");
+        assert_eq!((hex.get_start(), hex.get_end()), (Some(4), None));
+
+        let decimal = error("12a");
+        assert_eq!((decimal.get_start(), decimal.get_end()), (Some(2), None));
+
+        let empty = error("0x__ + 1");
+        assert_eq!((empty.get_start(), empty.get_end()), (Some(0), Some(4)));
     }
 
-    Ok(result)
+    #[test]
+    fn recovery_skips_literals_and_comments() {
+        let mut diagnostics = vec![];
+        let source = "a = \"x\\q; \\\" ;\";\n# it's; here\nb = ';';\nc = 0b2 ';' \";\" 1;\nd;";
+        let result = tokenize(&source.chars().collect::<Vec<_>>(), &mut diagnostics);
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+
+        let values: Vec<_> = result.iter().map(|i| i.value.as_str()).collect();
+        assert_eq!(values, ["# it's; here", "b", "=", "59", ";", "d", ";"]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(single(r#""a\nb\tc\\d""#).value, "a\nb\tc\\d");
+        assert_eq!(single(r#""\r\0\"\'""#).value, "\r\0\"'");
+        assert_eq!(single(r#""\u{48}\u{e9}\u{1F600}""#).value, "Hé😀");
+        assert_eq!(single(r"'\n'").value, "10");
+        assert_eq!(single(r"'\t'").value, "9");
+        assert_eq!(single(r"'\\'").value, "92");
+        assert_eq!(single(r"'\u{263a}'").value, "9786");
+    }
+
+    #[test]
+    fn non_ascii_literals() {
+        let token = single("\"héllo ☺\"");
+        assert_eq!(token.kind, TokenKind::String);
+        assert_eq!(token.value, "héllo ☺");
+        assert_eq!((token.start, token.end), (0, 9));
+
+        assert_eq!(single("'é'").value, "233");
+        assert_eq!(single("'😀'").value, "128512");
+
+        // Positions count characters, not bytes
+        let result = tokens("\"ééé\" 'ü' x");
+        assert_eq!(result[1].value, "252");
+        assert_eq!((result[1].start, result[1].end), (6, 9));
+        assert_eq!((result[2].start, result[2].end), (10, 11));
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let escape = error(r#""ab\q""#);
+        assert_eq!((escape.get_start(), escape.get_end()), (Some(3), Some(5)));
+
+        let code_point = error(r#""\u{d800}""#);
+        assert_eq!(
+            (code_point.get_start(), code_point.get_end()),
+            (Some(1), Some(9))
+        );
+
+        let unterminated = error(r"'ab'");
+        assert_eq!(unterminated.get_start(), Some(0));
+    }
 }
@@ -5,7 +5,6 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 extern "C" {
     fn handle_output(value: &str);
-    fn handle_error(value: &str);
 }
 
 pub struct FunctionWriter {
@@ -33,10 +32,4 @@ impl FunctionWriter {
             function: Box::new(|b: &str| handle_output(b)),
         }
     }
-
-    pub fn create_stderr() -> FunctionWriter {
-        FunctionWriter {
-            function: Box::new(|b: &str| handle_error(b)),
-        }
-    }
 }
@@ -0,0 +1,89 @@
+// Every test binary uses a different part of this module
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// A directory only used by one test, removed again when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "cell_tail_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    // Writes a file relative to the directory and returns its full path
+    pub fn write(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn cell_tail() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_cell_tail"))
+}
+
+// Runs `cell_tail <flags> <file> <args>` with the program in a temporary file
+pub fn run(flags: &[&str], program: &str, args: &[&str], stdin: &[u8]) -> Output {
+    let directory = TempDir::new();
+    let path = directory.write("program.ct", program);
+
+    let mut child = cell_tail()
+        .args(flags)
+        .arg(&path)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// The diagnostics printed with --error-format=json
+pub fn diagnostics(output: &Output) -> serde_json::Value {
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+    serde_json::from_str(if stderr.is_empty() { "[]" } else { &stderr }).unwrap()
+}
+
+// Runs a program with debug output on after the given attributes, returning stdout and the JSON diagnostics
+pub fn run_debug(program: &str, attributes: &str) -> (String, serde_json::Value) {
+    let output = run(
+        &["--error-format=json"],
+        &format!("I=1;\nO=N;\nD=true;\n{attributes}{program}"),
+        &[],
+        &[],
+    );
+    let diagnostics = diagnostics(&output);
+    (String::from_utf8(output.stdout).unwrap(), diagnostics)
+}
+
+// The rules printed by debug output, without the generation lines
+pub fn rules(stdout: &str) -> Vec<&str> {
+    stdout
+        .lines()
+        .filter(|i| i.contains(':') && !i.starts_with('\t'))
+        .collect()
+}
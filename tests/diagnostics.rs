@@ -0,0 +1,94 @@
+mod common;
+
+fn diagnostics(program: &str) -> serde_json::Value {
+    let output = common::run(&["--error-format=json"], program, &[], &[]);
+    assert!(!output.status.success());
+    common::diagnostics(&output)
+}
+
+#[test]
+fn several_errors_in_one_run() {
+    let diagnostics = diagnostics(concat!(
+        "I=1;\nO=N;\n",
+        "N, a, N: N, \"a\\q;\", N;\n",
+        "N, 2, N: N, (1, , N;\n",
+        "N, 3, N: N, b, N;\n",
+        "fn f x: g x;\n",
+        "N, 0b12, N: N, 1, N;\n",
+        "N, 4, N: N, 5, N;\n",
+    ));
+
+    let lines: Vec<_> = diagnostics
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["start"]["line"].as_u64().unwrap())
+        .collect();
+    assert_eq!(lines, [3, 4, 5, 6, 7], "{diagnostics}");
+}
+
+#[test]
+fn no_warnings_for_partly_parsed_programs() {
+    let diagnostics = diagnostics("I=1;\nO=N;\nfn f x: x;\nN, 1, N: N, f (1, , N;\n");
+
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1, "{diagnostics}");
+    assert_eq!(diagnostics[0]["severity"], "error");
+}
+
+fn errors(diagnostics: &serde_json::Value) -> Vec<&serde_json::Value> {
+    diagnostics
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|i| i["severity"] == "error")
+        .collect()
+}
+
+#[test]
+fn json_positions() {
+    let diagnostics = diagnostics("I=1;\nO=N;\nN, a, N: N, (\"é\", valeu), N;\n");
+    let errors = errors(&diagnostics);
+
+    assert_eq!(errors.len(), 1, "{diagnostics}");
+    assert_eq!(
+        errors[0]["message"],
+        "Reference to unkown variable \"valeu\""
+    );
+    // Bytes count the two bytes of é, columns count characters
+    assert_eq!(
+        errors[0]["start"],
+        serde_json::json!({"byte": 29, "line": 3, "column": 19})
+    );
+    assert_eq!(
+        errors[0]["end"],
+        serde_json::json!({"byte": 34, "line": 3, "column": 24})
+    );
+}
+
+#[test]
+fn json_positions_of_sub_expressions() {
+    let diagnostics = diagnostics(
+        "I=1;\nO=N;\n# allow: unused-variable\nN, (a, 1) | (1, b), N: N, 1, N;\nN, x, N: N, undefined x, N;\n",
+    );
+    let errors = errors(&diagnostics);
+    assert_eq!(errors.len(), 2, "{diagnostics}");
+
+    assert_eq!(
+        errors[0]["message"],
+        "Parts of OR expression define different variables"
+    );
+    assert_eq!(
+        (&errors[0]["start"], &errors[0]["end"]),
+        (
+            &serde_json::json!({"byte": 39, "line": 4, "column": 5}),
+            &serde_json::json!({"byte": 52, "line": 4, "column": 18})
+        )
+    );
+
+    assert_eq!(
+        errors[1]["message"],
+        "Call to undefined function \"undefined\""
+    );
+    assert_eq!(errors[1]["start"]["column"], 13);
+    assert_eq!(errors[1]["end"]["column"], 24);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+fn run_fmt(path: &std::path::Path, flags: &[&str]) -> bool {
+    common::cell_tail()
+        .arg("fmt")
+        .args(flags)
+        .arg(path)
+        .status()
+        .unwrap()
+        .success()
+}
+
+// Formats a program and returns the result
+fn format(program: &str) -> String {
+    let directory = common::TempDir::new();
+    let path = directory.write("program.ct", program);
+
+    assert!(run_fmt(&path, &[]));
+    std::fs::read_to_string(&path).unwrap()
+}
+
+#[test]
+fn aligns_rules() {
+    assert_eq!(
+        format("I = 1;O=N;\nN,a,N :N,a+1,N; # first\n(N, 1) | N,(a,b),N:N,\n  a,N;\n\nfn f(x):x;\n"
+        ),
+        "I=1;\nO=N;\nN,          a,      N: N, a + 1, N; # first\n(N, 1) | N, (a, b), N: N, a,     N;\n\nfn f(x): x;\n"
+    );
+}
+
+#[test]
+fn spaces_around_binary_operators() {
+    assert_eq!(
+        format("I=-1;\nO=N;\nN,a*2,N:N,a+1  -  (a%2),N;\nN, b & ..-5, N: N, b*-1, N;\n"),
+        "I=-1;\nO=N;\nN, a * 2,    N: N, a + 1 - (a % 2), N;\nN, b & ..-5, N: N, b * -1,          N;\n"
+    );
+    assert_eq!(
+        format("fn f -1: 2;\nfor d in (0..3) -1, d, N: N, d^2, N;\n"),
+        "fn f -1: 2;\nfor d in (0..3) -1, d, N: N, d ^ 2, N;\n"
+    );
+}
+
+#[test]
+fn keeps_comments() {
+    assert_eq!(
+        format("# header\n\n\nI=1;\nO=N;\nN, a, N: N,\n# inside\na, N;\n# end"),
+        "# header\n\nI=1;\nO=N;\n# inside\nN, a, N: N, a, N;\n# end\n"
+    );
+}
+
+#[test]
+fn idempotent() {
+    for example in std::fs::read_dir("examples").unwrap() {
+        let example = example.unwrap().path();
+        let formatted = format(&std::fs::read_to_string(&example).unwrap());
+        assert_eq!(format(&formatted), formatted, "{example:?}");
+    }
+}
+
+#[test]
+fn check() {
+    let directory = common::TempDir::new();
+    let path = directory.write("program.ct", "I=1;O=N;");
+    assert!(!run_fmt(&path, &["--check"]));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "I=1;O=N;");
+
+    directory.write("program.ct", "I=1;\nO=N;\n");
+    assert!(run_fmt(&path, &["--check"]));
+}
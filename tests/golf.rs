@@ -0,0 +1,45 @@
+mod common;
+
+// Golfs a program and returns the result and the reported byte count
+fn golf(flags: &[&str], program: &str) -> (String, String) {
+    let output = common::run(&[&["golf"], flags].concat(), program, &[], &[]);
+
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .trim_end()
+            .to_owned(),
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .trim_end()
+            .to_owned(),
+    )
+}
+
+#[test]
+fn minimal_whitespace() {
+    let (golfed, bytes) = golf(&[],
+        "Input = STDIN numbers;\nOutput = numbers;\nDebug = false;\n# A comment\nN, value, N: N, value - -0x10, N; # Another one\nN, 'a', N: N, 1, N;\n",
+    );
+    assert_eq!(golfed, "I=I N;O=N;N,value,N:N,value- -16,N;N,97,N:N,1,N;");
+    assert_eq!(bytes, "48 bytes");
+}
+
+#[test]
+fn rename_variables() {
+    let (golfed, _) = golf(&["--rename"],
+        "I=1;\nO=N;\nfn double x: x * 2;\nN, value, N: N, double value, N;\n(first, second), N, N: N, first, second;\n",
+    );
+    assert_eq!(
+        golfed,
+        "I=1;O=N;fn double a:a*2;N,a,N:N,double a,N;(a,b),N,N:N,a,b;"
+    );
+}
+
+#[test]
+fn compact_constant_input() {
+    let (golfed, bytes) = golf(&[], "I = (1, 2), [3, 4], \"ab\", N;\nO = N;\nN, x, N: x;\n");
+    assert_eq!(golfed, "I=(1,2),[3,4],\"ab\",N;O=N;N,x,N:x;");
+    assert_eq!(bytes, format!("{} bytes", golfed.len()));
+}
@@ -0,0 +1,136 @@
+mod common;
+
+// Writes the files into a temporary directory and runs the first one,
+// returning stdout and the JSON diagnostics
+fn run(files: &[(&str, &str)]) -> (String, serde_json::Value) {
+    let directory = common::TempDir::new();
+    for (name, contents) in files {
+        directory.write(name, contents);
+    }
+
+    let output = common::cell_tail()
+        .arg("--error-format=json")
+        .arg(directory.path().join(files[0].0))
+        .output()
+        .unwrap();
+    let diagnostics = common::diagnostics(&output);
+    (String::from_utf8(output.stdout).unwrap(), diagnostics)
+}
+
+fn messages(diagnostics: &serde_json::Value) -> Vec<&str> {
+    diagnostics
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["message"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn relative_paths() {
+    let (stdout, diagnostics) = run(&[
+        (
+            "main.ct",
+            "import \"lib/a.ct\";\nI=1;\nO=N;\nN, 1, N: N, a 1 + b 1, N;\n",
+        ),
+        ("lib/a.ct", "import \"b.ct\";\nfn a x: x + 1;\n"),
+        ("lib/b.ct", "fn b x: x * 10;\n"),
+    ]);
+    assert_eq!(diagnostics, serde_json::json!([]));
+    assert_eq!(stdout.trim_end().trim_end_matches(','), "12");
+}
+
+#[test]
+fn shared_import_is_loaded_once() {
+    let (stdout, diagnostics) = run(&[
+        (
+            "main.ct",
+            "import \"a.ct\";\nimport \"b.ct\";\nI=1;\nO=N;\nN, 1, N: N, a 1 + b 1 + c 1, N;\n",
+        ),
+        ("a.ct", "import \"c.ct\";\nfn a x: x;\n"),
+        ("b.ct", "import \"c.ct\";\nfn b x: x;\n"),
+        ("c.ct", "fn c x: x + 1;\n"),
+    ]);
+    assert_eq!(diagnostics, serde_json::json!([]));
+    assert_eq!(stdout.trim_end().trim_end_matches(','), "4");
+}
+
+#[test]
+fn import_cycle() {
+    let (_, diagnostics) = run(&[
+        (
+            "main.ct",
+            "import \"a.ct\";\nI=1;\nO=N;\nN, x, N: N, a x, N;\n",
+        ),
+        ("a.ct", "import \"b.ct\";\nfn a x: x;\n"),
+        ("b.ct", "import \"a.ct\";\nfn b x: x;\n"),
+    ]);
+    let messages = messages(&diagnostics);
+    assert_eq!(messages.len(), 1, "{messages:?}");
+    assert!(messages[0].starts_with("Import cycle detected"));
+    assert!(diagnostics[0]["file"].as_str().unwrap().ends_with("b.ct"));
+}
+
+#[test]
+fn function_name_clash() {
+    let (_, diagnostics) = run(&[
+        (
+            "main.ct",
+            "import \"lib.ct\";\nI=1;\nO=N;\nfn f x: x;\nN, x, N: N, f x, N;\n",
+        ),
+        ("lib.ct", "fn f x: x + 1;\n"),
+    ]);
+    assert_eq!(
+        messages(&diagnostics),
+        ["Function \"f\" is defined more than once"]
+    );
+    let error = &diagnostics[0];
+    assert!(error["file"].as_str().unwrap().ends_with("main.ct"));
+    assert_eq!(error["start"]["line"], 4);
+    assert!(error["related"][0]["file"]
+        .as_str()
+        .unwrap()
+        .ends_with("lib.ct"));
+    assert_eq!(error["related"][0]["start"]["line"], 1);
+}
+
+#[test]
+fn prelude_name_clash() {
+    let (_, diagnostics) = run(&[(
+        "main.ct",
+        "import prelude;\nI=1;\nO=N;\nfn len x: 0;\nN, x, N: N, len x, N;\n",
+    )]);
+    assert_eq!(
+        messages(&diagnostics),
+        ["Function \"len\" is defined more than once"]
+    );
+    assert_eq!(diagnostics[0]["related"][0]["file"], "<prelude>");
+}
+
+#[test]
+fn imported_rules_and_attributes() {
+    let (_, diagnostics) = run(&[
+        (
+            "main.ct",
+            "import \"lib.ct\";\nI=1;\nO=N;\nN, x, N: N, x, N;\n",
+        ),
+        ("lib.ct", "N, x, N: N, x, N;\n"),
+    ]);
+    assert_eq!(
+        messages(&diagnostics),
+        ["Imported files may only contain functions and imports, not rules"]
+    );
+
+    let (_, diagnostics) = run(&[
+        (
+            "main.ct",
+            "import \"lib.ct\";\nI=1;\nO=N;\nN, x, N: N, x, N;\n",
+        ),
+        ("lib.ct", "fn f x: x;\nO=C;\n"),
+    ]);
+    assert_eq!(
+        messages(&diagnostics),
+        ["Imported files may only contain functions and imports, not attributes"]
+    );
+    assert_eq!(diagnostics[0]["start"]["line"], 2);
+}
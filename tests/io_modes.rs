@@ -0,0 +1,177 @@
+mod common;
+
+use std::process::Output;
+
+// Runs a program and returns what it printed
+fn run(flags: &[&str], program: &str, input: &[&str]) -> String {
+    let output = common::run(flags, program, input, &[]);
+
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const VALUES: &str =
+    "I=1,2,3;\nM=5;\nN, 1, N: N, \"hi\", N;\nN, 2, N: N, [1, -2], N;\nN, 3, N: N, (3, 4), N;\n";
+
+#[test]
+fn values_output() {
+    assert_eq!(
+        run(&[], &format!("O=V;\n{VALUES}"), &[]),
+        "\"hi\", [1, -2], (3, 4)\n"
+    );
+}
+
+#[test]
+fn json_output() {
+    let output = run(&["--output=json"], VALUES, &[]);
+    let values: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(
+        values,
+        serde_json::json!([[104, [105, null]], [1, [-2, null]], [3, 4]])
+    );
+}
+
+#[test]
+fn json_input() {
+    let output = run(
+        &["--output=values"],
+        "I=CMD JSON;\nM=5;\nN, 5, N: N, 6, N;\n",
+        &[r#"[5, null, "hi", [1, [2, null]], [-3, []]]"#],
+    );
+
+    assert_eq!(output, "6, \"hi\", [1, 2], (-3, ())\n");
+}
+
+#[test]
+fn single_value_tuple_in_values_output() {
+    let output = common::run(
+        &["--error-format=json"],
+        "I=CMD JSON;\nO=V;\nM=5;\nN, 0, N: N, 0, N;\n",
+        &["[[5]]"],
+        &[],
+    );
+    assert!(!output.status.success());
+    let diagnostics = common::diagnostics(&output);
+    assert!(
+        diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Can't output (5) as a value"),
+        "{diagnostics}"
+    );
+
+    let output = run(
+        &["--output=json"],
+        "I=CMD JSON;\nM=5;\nN, 0, N: N, 0, N;\n",
+        &["[[5]]"],
+    );
+    assert_eq!(output, "[[5]]\n");
+}
+
+#[test]
+fn constant_input() {
+    let output = run(
+        &["--output=values"],
+        "I=(1, 2), [3, 4], \"hi\", N, -5, 'a';\nM=5;\nN, 0, N: N, 0, N;\n",
+        &[],
+    );
+    assert_eq!(output, "(1, 2), [3, 4], \"hi\", -5, 97\n");
+
+    let output = run(
+        &["--output=values"],
+        "I=\"hi\";\nM=5;\nN, 0, N: N, 0, N;\n",
+        &[],
+    );
+    assert_eq!(output, "104, 105\n");
+}
+
+#[test]
+fn empty_constant_input_value() {
+    for (program, start, end) in [("I=1,,2;\n", 4, 6), ("I=,;\n", 3, 4), ("I=1,;\n", 4, 5)] {
+        let output = common::run(&["--error-format=json"], program, &[], &[]);
+        let diagnostics = common::diagnostics(&output);
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1, "{diagnostics}");
+        let error = &diagnostics[0];
+        assert_eq!(error["message"], "Expected a value between commas in Input");
+        assert_eq!(error["start"]["column"], start, "{program}");
+        assert_eq!(error["end"]["column"], end, "{program}");
+    }
+}
+
+#[test]
+fn lines() {
+    let output = run(
+        &["--output=lines"],
+        "I=CMD LINES;\nM=5;\nN, 0, N: N, 0, N;\n",
+        &["first line\nsecond\n"],
+    );
+
+    assert_eq!(output, "first line\nsecond\n");
+}
+
+#[test]
+fn numbers_separated_by_whitespace() {
+    let output = run(
+        &[],
+        "I=CMD NUMBERS;\nO=N;\nM=5;\nN, 0, N: N, 0, N;\n",
+        &["1, 2\n3   -4\n"],
+    );
+
+    assert_eq!(output, "1, 2, 3, -4, \n");
+}
+
+// Runs a program with the given bytes as STDIN
+fn run_with_stdin(program: &str, stdin: &[u8]) -> Output {
+    common::run(&[], program, &[], stdin)
+}
+
+#[test]
+fn bytes() {
+    let input = [0x00, 0xff, 0xc3, 0x28, 0x0a];
+    let output = run_with_stdin(
+        "I=STDIN BYTES;\nO=BYTES;\nM=5;\nN, 0, N: N, 0, N;\n",
+        &input,
+    );
+    assert!(output.status.success());
+    assert_eq!(output.stdout, input);
+
+    let output = run_with_stdin(
+        "I=STDIN BYTES;\nO=BYTES;\nM=5;\nN, 1, N: N, 300, N;\n",
+        &[1],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Can't output 300 as a byte"));
+}
+
+#[test]
+fn blank_lines_are_dropped() {
+    let output = run_with_stdin(
+        "I=STDIN LINES;\nO=LINES;\nM=5;\nN, 0, N: N, 0, N;\n",
+        b"ab\n\ncd\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"ab\ncd\n");
+}
+
+#[test]
+fn invalid_utf8_is_an_error() {
+    let output = run_with_stdin("I=STDIN CHARS;\nM=5;\nN, 0, N: N, 0, N;\n", &[0xc3, 0x28]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("not valid UTF-8"));
+}
+
+#[test]
+fn unknown_output_mode() {
+    let output = common::run(&["--output=byts"], "I=1;\n", &[], &[]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("expected one of characters, numbers, values, json, lines, bytes"));
+}
@@ -0,0 +1,65 @@
+mod common;
+
+#[test]
+fn mirrored_rule_follows_original() {
+    let (stdout, _) = common::run_debug(
+        "N, 1, N: N, 2, N;\nmirror (a, 1), b, _: b, a, b;\nN, 3, N: N, 4, N;\n",
+        "M=1;\n",
+    );
+    assert_eq!(
+        common::rules(&stdout),
+        [
+            "N, 1, N: N, 2, N;",
+            "(a, 1), b, _: b, a, b;",
+            "_, b, (a, 1): b, a, b;",
+            "N, 3, N: N, 4, N;",
+        ]
+    );
+}
+
+#[test]
+fn mirrored_template_rules_stay_paired() {
+    let (stdout, _) = common::run_debug("mirror for x in [1, 2] x, N, _: N, x, N;\n", "M=1;\n");
+    assert_eq!(
+        common::rules(&stdout),
+        [
+            "1, N, _: N, 1, N;",
+            "_, N, 1: N, 1, N;",
+            "2, N, _: N, 2, N;",
+            "_, N, 2: N, 2, N;",
+        ]
+    );
+}
+
+#[test]
+fn symmetric_rule_is_not_duplicated() {
+    let (stdout, diagnostics) = common::run_debug("mirror a, N, a: N, a, N;\n", "M=1;\n");
+    assert_eq!(diagnostics, serde_json::json!([]));
+    assert_eq!(common::rules(&stdout), ["a, N, a: N, a, N;"]);
+}
+
+#[test]
+fn unmirrorable_rule() {
+    let (_, diagnostics) = common::run_debug("mirror x: x;\n", "M=1;\n");
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    let error = &diagnostics[0];
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("the pattern must be a 3 tuple"));
+    assert_eq!(error["start"]["line"], 5);
+    assert_eq!(error["start"]["column"], 8);
+    assert_eq!(error["end"]["column"], 9);
+}
+
+#[test]
+fn unmirrorable_result() {
+    let (_, diagnostics) = common::run_debug("mirror a, b, c: a + b;\n", "M=1;\n");
+    let error = &diagnostics[0];
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("the result must be written as a 3 tuple"));
+    assert_eq!(error["start"]["column"], 17);
+    assert_eq!(error["end"]["column"], 22);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+// Evaluates a single expression with the prelude imported and returns the
+// first number of the result, as printed by the numbers output mode
+fn evaluate(expression: &str) -> String {
+    let output = common::run(
+        &[],
+        &format!("import prelude;\nI=-1;\nO=N;\nN,-1,N: N, {expression}, N;\n"),
+        &[],
+        &[],
+    );
+
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .trim_end()
+        .trim_end_matches(',')
+        .to_owned()
+}
+
+#[test]
+fn is_empty() {
+    assert_eq!(evaluate("is_empty N"), "1");
+    assert_eq!(evaluate("(is_empty [1], 0)"), "0");
+    assert_eq!(evaluate("(is_empty \"\" + 5, 0)"), "6");
+}
+
+#[test]
+fn head_and_tail() {
+    assert_eq!(evaluate("head \"abc\""), "97");
+    assert_eq!(evaluate("tail \"abc\""), "98");
+    assert_eq!(evaluate("head N + 7"), "7");
+}
+
+#[test]
+fn len() {
+    assert_eq!(evaluate("(len N + 1, 0)"), "1");
+    assert_eq!(evaluate("len \"hello\""), "5");
+    assert_eq!(
+        evaluate("len [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]"),
+        "16"
+    );
+}
+
+#[test]
+fn eq() {
+    assert_eq!(evaluate("eq (\"abc\", \"abc\")"), "1");
+    assert_eq!(evaluate("(eq (\"abc\", \"abd\") + 5, 0)"), "5");
+    assert_eq!(evaluate("eq (3, 3)"), "1");
+}
+
+#[test]
+fn compare() {
+    assert_eq!(evaluate("compare (\"abc\", \"abd\")"), "-1");
+    assert_eq!(evaluate("compare (\"ab\", \"abc\")"), "-1");
+    assert_eq!(evaluate("compare (\"b\", \"abc\")"), "1");
+    assert_eq!(evaluate("(compare (\"ab\", \"ab\") + 5, 0)"), "5");
+}
+
+#[test]
+fn min_max() {
+    assert_eq!(evaluate("min (3, 8)"), "3");
+    assert_eq!(evaluate("min (8, 3)"), "3");
+    assert_eq!(evaluate("max (3, 8)"), "8");
+    assert_eq!(evaluate("max (8, 3)"), "8");
+}
+
+#[test]
+fn abs_sign() {
+    assert_eq!(evaluate("abs (0-12)"), "12");
+    assert_eq!(evaluate("abs 12"), "12");
+    assert_eq!(evaluate("sign (0-12)"), "-1");
+    assert_eq!(evaluate("(sign 0 + 5, 0)"), "5");
+    assert_eq!(evaluate("sign 12"), "1");
+}
+
+#[test]
+fn digits() {
+    assert_eq!(evaluate("is_digit '7'"), "1");
+    assert_eq!(evaluate("(is_digit 'a' + 5, 0)"), "5");
+    assert_eq!(evaluate("digit '7'"), "7");
+    assert_eq!(evaluate("digit 'a' + 3"), "3");
+    assert_eq!(evaluate("digit_char 7"), "55");
+    assert_eq!(evaluate("digit_char 12 + 3"), "3");
+}
+
+#[test]
+fn letters() {
+    assert_eq!(evaluate("is_upper 'Q'"), "1");
+    assert_eq!(evaluate("(is_upper 'q' + 5, 0)"), "5");
+    assert_eq!(evaluate("is_lower 'q'"), "1");
+    assert_eq!(evaluate("(is_lower 'Q' + 5, 0)"), "5");
+    assert_eq!(evaluate("to_upper 'q'"), "81");
+    assert_eq!(evaluate("to_upper '1'"), "49");
+    assert_eq!(evaluate("to_lower 'Q'"), "113");
+    assert_eq!(evaluate("to_lower '1'"), "49");
+}
+
+#[test]
+fn is_space() {
+    assert_eq!(evaluate("is_space ' '"), "1");
+    assert_eq!(evaluate("is_space '\\n'"), "1");
+    assert_eq!(evaluate("(is_space 'a' + 5, 0)"), "5");
+}
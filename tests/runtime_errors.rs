@@ -0,0 +1,43 @@
+mod common;
+
+// Runs a program that fails at runtime and returns the context of the error
+fn runtime_context(program: &str) -> serde_json::Value {
+    let output = common::run(&["--error-format=json"], program, &[], &[]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // Warnings are printed before running, the runtime error comes last
+    let diagnostics: serde_json::Value =
+        serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    diagnostics[0]["context"].clone()
+}
+
+#[test]
+fn failed_function_call() {
+    let context = runtime_context(
+        "I=1,2;\nO=N;\nS=true;\nfn f 1: 2;\n# allow: unused-variable\n_, b, _: N, f(b), N;\n",
+    );
+
+    assert_eq!(context["generation"], 1);
+    assert_eq!(context["cell"], 1);
+    assert_eq!(context["rule"]["start"]["line"], 6);
+}
+
+#[test]
+fn exceeded_max() {
+    let context = runtime_context("I=1;\nO=N;\nM=3;\nN, a, N: N, a + 1, N;\n");
+
+    assert_eq!(context["generation"], 4);
+    assert!(context["cell"].is_null());
+}
+
+#[test]
+fn readable_input() {
+    let program = "S=true;\nfn f 1: 2;\nN, 1, N: N, \"hi\", N;\n_, b, _: N, f(b), N;\n";
+
+    let context = runtime_context(&format!("I=1;\n{program}"));
+    assert_eq!(context["input"], "(N, \"hi\", N)");
+
+    let context = runtime_context(&format!("I=1;\nO=N;\n{program}"));
+    assert_eq!(context["input"], "(N, [104, 105], N)");
+}
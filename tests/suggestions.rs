@@ -0,0 +1,38 @@
+mod common;
+
+// Runs a program that fails to load and returns the suggestions of its errors
+fn suggestions(program: &str) -> Vec<String> {
+    let output = common::run(&["--error-format=json"], program, &[], &[]);
+
+    assert!(!output.status.success());
+    common::diagnostics(&output)
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|i| i["suggestions"].as_array().unwrap().clone())
+        .map(|i| i.as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn misspelled_names() {
+    assert_eq!(
+        suggestions("I=1;\nO=N;\nfn double x: x * 2;\nN, a, N: N, doubel(a), N;\n"),
+        ["double"]
+    );
+    assert_eq!(
+        suggestions("I=1;\nO=N;\nN, value, N: N, valeu, N;\n"),
+        ["value"]
+    );
+    assert!(suggestions("I=1;\nO=N;\nN, a, N: N, xyz, N;\n").is_empty());
+}
+
+#[test]
+fn misspelled_attributes() {
+    assert_eq!(suggestions("Inptu=1;\nO=N;\n"), ["Input"]);
+    assert_eq!(suggestions("I=1;\nO=N;\nMxa=10;\n"), ["Max"]);
+    assert_eq!(suggestions("I=1;\nO=Numbres;\n"), ["numbers"]);
+    assert_eq!(suggestions("I=1;\nO=lnes;\n"), ["lines"]);
+    assert_eq!(suggestions("I=1;\nO=byts;\n"), ["bytes"]);
+    assert_eq!(suggestions("I=1;\nO=N;\nS=ture;\n"), ["true"]);
+}
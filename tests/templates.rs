@@ -0,0 +1,81 @@
+mod common;
+
+fn single_error(diagnostics: &serde_json::Value) -> (&str, u64, u64) {
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1, "{diagnostics}");
+    let error = &diagnostics[0];
+    (
+        error["message"].as_str().unwrap(),
+        error["start"]["column"].as_u64().unwrap(),
+        error["end"]["column"].as_u64().unwrap(),
+    )
+}
+
+#[test]
+fn list_template() {
+    let (stdout, diagnostics) = common::run_debug("for c in ['a', (1, 2)] N, c, N: N, 0, N;\n", "");
+    assert_eq!(diagnostics, serde_json::json!([]));
+    assert_eq!(
+        common::rules(&stdout),
+        ["N, 97, N: N, 0, N;", "N, (1, 2), N: N, 0, N;"]
+    );
+}
+
+#[test]
+fn range_template() {
+    let (stdout, _) = common::run_debug("for d in (-2..2) N, d, N: N, d * 2, N;\n", "");
+    assert_eq!(
+        common::rules(&stdout),
+        [
+            "N, -1, N: N, (-1) * 2, N;",
+            "N, 0, N: N, 0 * 2, N;",
+            "N, 1, N: N, 1 * 2, N;",
+        ]
+    );
+}
+
+#[test]
+fn empty_range() {
+    for range in ["(3..3)", "(3..4)", "(5..0)"] {
+        let (stdout, diagnostics) =
+            common::run_debug(&format!("for d in {range} N, d, N: N, d, N;\n"), "");
+        assert_eq!(diagnostics, serde_json::json!([]));
+        assert!(common::rules(&stdout).is_empty(), "{range}: {stdout}");
+    }
+}
+
+#[test]
+fn range_start_overflow() {
+    let (_, diagnostics) =
+        common::run_debug("for d in (9223372036854775807..0) N, d, N: N, d, N;\n", "");
+    let (message, start, end) = single_error(&diagnostics);
+    assert!(message.contains("too large"), "{message}");
+    assert_eq!((start, end), (11, 33));
+}
+
+#[test]
+fn huge_range() {
+    let (_, diagnostics) = common::run_debug("for d in (0..100000000) N, d, N: N, d, N;\n", "");
+    let (message, start, end) = single_error(&diagnostics);
+    assert!(message.contains("more than"), "{message}");
+    assert_eq!((start, end), (11, 23));
+}
+
+#[test]
+fn huge_nested_templates() {
+    let (_, diagnostics) = common::run_debug(
+        "for a in (0..1000) for b in (0..1000) N, (a, b), N: N, a, N;\n",
+        "",
+    );
+    let (message, start, _) = single_error(&diagnostics);
+    assert!(message.contains("more than"), "{message}");
+    assert_eq!(start, 1);
+}
+
+#[test]
+fn error_inside_template() {
+    let (_, diagnostics) = common::run_debug("for x in [1] N, x, N: N, y, N;\n", "");
+    let (message, start, end) = single_error(&diagnostics);
+    assert!(message.contains("\"y\""), "{message}");
+    assert_eq!(diagnostics[0]["start"]["line"], 4);
+    assert_eq!((start, end), (26, 27));
+}
@@ -0,0 +1,170 @@
+mod common;
+
+fn warning_codes(program: &str) -> Vec<String> {
+    warning_codes_with_flags(&[], program)
+}
+
+// Runs a program and returns the codes of all warnings it produced
+fn warning_codes_with_flags(flags: &[&str], program: &str) -> Vec<String> {
+    let output = common::run(
+        &[&["--error-format=json"], flags].concat(),
+        &format!("I=1;\nO=N;\nM=5;\n{program}"),
+        &[],
+        &[],
+    );
+
+    assert!(output.status.success());
+    common::diagnostics(&output)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["code"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn clean_program() {
+    assert!(warning_codes("N, a & 1, N: N, a + 1, N;\n").is_empty());
+}
+
+#[test]
+fn unused_variable() {
+    assert_eq!(warning_codes("N, a, b: N, a, N;\n"), ["unused-variable"]);
+    assert!(warning_codes("N, a, _b: N, a, N;\n").is_empty());
+    assert!(warning_codes("a, a, N: N, 1, N;\n").is_empty());
+}
+
+#[test]
+fn unused_variable_position() {
+    let output = common::run(
+        &["--error-format=json"],
+        "I=1;\nO=N;\nN, (a, bc), N: N, a, N;\n",
+        &[],
+        &[],
+    );
+    let warning = &common::diagnostics(&output)[0];
+
+    assert_eq!(warning["message"], "Variable \"bc\" is never used");
+    assert_eq!(warning["start"]["column"], 8);
+    assert_eq!(warning["end"]["column"], 10);
+}
+
+#[test]
+fn unreachable_rule() {
+    assert_eq!(
+        warning_codes("N, a, N: N, a, N;\nN, 1, N: N, 2, N;\n"),
+        ["unreachable-rule"]
+    );
+    assert!(warning_codes("mirror N, a, _: N, a, N;\n").is_empty());
+}
+
+#[test]
+fn unreachable_rule_ranges() {
+    assert_eq!(
+        warning_codes(
+            "N, ..0, N: N, 1, N;\nN, 0, N: N, 2, N;\nN, 0.., N: N, 3, N;\nN, a, N: N, a, N;\n"
+        ),
+        ["unreachable-rule"]
+    );
+    assert!(warning_codes("N, 0..5, N: N, 1, N;\nN, 3..10, N: N, 2, N;\nN, (1, 2), N: N, 3, N;\nN, (1, _), N: N, 4, N;\n"
+    )
+    .is_empty());
+    assert_eq!(
+        warning_codes("fn f (a, b): a + b;\nfn f (1, 2): 3;\nN, 1, N: N, f (1, 1), N;\n"),
+        ["unreachable-rule"]
+    );
+}
+
+#[test]
+fn unused_function() {
+    assert_eq!(
+        warning_codes("fn f x: x;\nN, a, N: N, a, N;\n"),
+        ["unused-function"]
+    );
+    assert!(warning_codes("import prelude;\nN, a, N: N, a, N;\n").is_empty());
+}
+
+#[test]
+fn duplicate_attribute() {
+    assert_eq!(
+        warning_codes("O=C;\nN, a, N: N, a, N;\n"),
+        ["duplicate-attribute"]
+    );
+}
+
+#[test]
+fn rule_result() {
+    assert_eq!(warning_codes("N, 5, N: N, 5;\n"), ["rule-result"]);
+}
+
+#[test]
+fn non_exhaustive() {
+    assert_eq!(
+        warning_codes("fn f ..0: 1;\nfn f 0..: 2;\nN, 5, N: N, f 5, N;\n"),
+        ["non-exhaustive"]
+    );
+    assert!(warning_codes("fn f ..0: 1;\nfn f 0: 2;\nfn f 0..: 3;\nfn g (a, 0): a;\nfn g (_, b): b;\nN, 5, N: N, f 5 + g (1, 2), N;\n"
+    )
+    .is_empty());
+}
+
+#[test]
+fn strict_function_calls() {
+    let output = common::run(
+        &[],
+        "I=5;\nO=N;\nS=true;\n# allow: non-exhaustive\nfn f 1: 2;\nN, x, N: N, f x, N;\n",
+        &[],
+        &[],
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("None of the patterns of function \"f\" match"));
+}
+
+#[test]
+fn failed_call_at_runtime() {
+    let program = "I=1,2,2;\nO=N;\n# allow: non-exhaustive\nfn f 1: 2;\nN, x, N: N, f x, N;\n";
+    let output = common::run(&["--error-format=json"], program, &[], &[]);
+    assert!(output.status.success());
+
+    // Reported once for the call, not every time it fails
+    let warnings = common::diagnostics(&output);
+    assert_eq!(warnings.as_array().unwrap().len(), 1, "{warnings}");
+    assert_eq!(warnings[0]["code"], "non-exhaustive");
+    assert_eq!(warnings[0]["start"]["line"], 5);
+    assert_eq!(warnings[0]["start"]["column"], 13);
+
+    let output = common::run(
+        &["--error-format=json"],
+        &program.replace("N, x", "# allow: non-exhaustive\nN, x"),
+        &[],
+        &[],
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn shape_mismatch() {
+    let program = "N, 1, N: N, 2, (1, 2);\n(a, b, c), 2, N: N, a + b + c, N;\n";
+    assert!(warning_codes(program).is_empty());
+    assert_eq!(
+        warning_codes_with_flags(&["--infer-shapes"], program),
+        ["shape-mismatch"]
+    );
+    assert!(warning_codes_with_flags(
+        &["--infer-shapes"],
+        "N, 1, N: N, 2, (1, 2);\n(a, b), 2, N: N, a + b, N;\n"
+    )
+    .is_empty());
+}
+
+#[test]
+fn allow_comment() {
+    assert!(warning_codes("N, a, b: N, a, N; # allow: unused-variable\n").is_empty());
+    assert!(warning_codes("# allow: unused-variable\nN, a, b: N, a, N;\n").is_empty());
+    assert_eq!(
+        warning_codes("# allow: unused-function\nN, a, b: N, a, N;\n"),
+        ["unused-variable"]
+    );
+}
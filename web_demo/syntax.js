@@ -14,7 +14,7 @@ ace.define("ace/mode/celltail_highlight_rules", [
         var CellTailHighlightRules = function () {
 
             var keywords = (
-                "fn|I|INPUT|D|DEBUG|O|OUTPUT"
+                "fn|mirror|for|in|import|I|INPUT|D|DEBUG|O|OUTPUT|S|STRICT"
             );
 
             var builtinConstants = (
@@ -34,8 +34,11 @@ ace.define("ace/mode/celltail_highlight_rules", [
                 "keyword": keywords
             }, "identifier");
 
-            var decimalInteger = "(?:(?:[1-9]\\d*)|(?:0))";
-            var integer = "(?:" + decimalInteger + ")";
+            var decimalInteger = "(?:\\d[\\d_]*)";
+            var hexInteger = "(?:0[xX][\\dA-Fa-f_]+)";
+            var octInteger = "(?:0[oO][0-7_]+)";
+            var binInteger = "(?:0[bB][01_]+)";
+            var integer = "(?:" + hexInteger + "|" + octInteger + "|" + binInteger + "|" + decimalInteger + ")";
 
             this.$rules = {
                 "start": [{
@@ -47,7 +50,7 @@ ace.define("ace/mode/celltail_highlight_rules", [
                     next: "qstring"
                 }, {
                     token: "string",           // Character
-                    regex: "'.'",
+                    regex: "'(?:\\\\u\\{[0-9a-fA-F]*\\}|\\\\.|[^\\\\'])'",
                 }, {
                     token: "keyword.operator",
                     regex: "\\+|\\-|\\*|\\/|\\||\\&|%|\\^|\\.\\."
@@ -70,6 +73,10 @@ ace.define("ace/mode/celltail_highlight_rules", [
                     include: "constants"
                 }],
                 "qstring": [
+                    {
+                        token: "constant.language.escape",
+                        regex: "\\\\(?:u\\{[0-9a-fA-F]*\\}|.)",
+                    },
                     {
                         token: "string",
                         regex: "\"",
@@ -77,7 +84,7 @@ ace.define("ace/mode/celltail_highlight_rules", [
                     },
                     {
                         token: "string",
-                        regex: "[^\"]",
+                        regex: "[^\"\\\\]",
                         next: "qstring"
                     }
 